@@ -0,0 +1,278 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{list::List, map::Map, types::Value};
+
+/// Conversion of a Rust value into a Lox [`Value`].
+pub trait IntoLox {
+    fn into_lox(self) -> Value;
+}
+
+/// Conversion of a Lox [`Value`] into a Rust value. Fails with a short,
+/// lowercase description such as `"expected number but got string"`.
+pub trait FromLox: Sized {
+    fn from_lox(value: Value) -> Result<Self, String>;
+}
+
+fn mismatch(expected: &str, value: &Value) -> String {
+    format!("expected {} but got {}", expected, value.type_name())
+}
+
+impl IntoLox for Value {
+    fn into_lox(self) -> Value {
+        self
+    }
+}
+
+impl FromLox for Value {
+    fn from_lox(value: Value) -> Result<Self, String> {
+        Ok(value)
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl FromLox for bool {
+    fn from_lox(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Boolean(boolean) => Ok(boolean),
+            value => Err(mismatch("boolean", &value)),
+        }
+    }
+}
+
+impl IntoLox for String {
+    fn into_lox(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl FromLox for String {
+    fn from_lox(value: Value) -> Result<Self, String> {
+        match value {
+            Value::String(string) => Ok(string),
+            value => Err(mismatch("string", &value)),
+        }
+    }
+}
+
+macro_rules! impl_float {
+    ($($float:ty),*) => {
+        $(
+            impl IntoLox for $float {
+                fn into_lox(self) -> Value {
+                    Value::Number(self as f64)
+                }
+            }
+
+            impl FromLox for $float {
+                fn from_lox(value: Value) -> Result<Self, String> {
+                    match value {
+                        Value::Number(number) => Ok(number as $float),
+                        value => Err(mismatch("number", &value)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_float!(f32, f64);
+
+// Lox only has doubles, so integers convert from numbers that have no
+// fractional part and fit the target type.
+macro_rules! impl_integer {
+    ($($integer:ty),*) => {
+        $(
+            impl IntoLox for $integer {
+                fn into_lox(self) -> Value {
+                    Value::Number(self as f64)
+                }
+            }
+
+            impl FromLox for $integer {
+                fn from_lox(value: Value) -> Result<Self, String> {
+                    match value {
+                        Value::Number(number)
+                            if number.fract() == 0.0
+                                && number >= <$integer>::MIN as f64
+                                // `MAX as f64` rounds up to `MAX + 1` for
+                                // 64-bit types, so the bound is exclusive.
+                                && number < <$integer>::MAX as f64 + 1.0 =>
+                        {
+                            Ok(number as $integer)
+                        }
+                        Value::Number(number) => Err(format!(
+                            "expected {} but got {}",
+                            stringify!($integer),
+                            number
+                        )),
+                        value => Err(mismatch("number", &value)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// `None` is `nil`.
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Value {
+        match self {
+            Some(value) => value.into_lox(),
+            None => Value::Nil,
+        }
+    }
+}
+
+impl<T: FromLox> FromLox for Option<T> {
+    fn from_lox(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::from_lox(value).map(Some),
+        }
+    }
+}
+
+/// Creates a new Lox list; the script and the host don't share it afterwards.
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Value {
+        let elements: List = self.into_iter().map(IntoLox::into_lox).collect();
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+}
+
+/// Copies the elements out of a Lox list.
+impl<T: FromLox> FromLox for Vec<T> {
+    fn from_lox(value: Value) -> Result<Self, String> {
+        match value {
+            Value::List(list) => list
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(index, element)| {
+                    T::from_lox(element.clone())
+                        .map_err(|message| format!("element {}: {}", index, message))
+                })
+                .collect(),
+            value => Err(mismatch("list", &value)),
+        }
+    }
+}
+
+/// Creates a new Lox map. Entries are inserted in key order, so the map
+/// iterates the same way every time.
+impl<T: IntoLox> IntoLox for HashMap<String, T> {
+    fn into_lox(self) -> Value {
+        let mut entries: Vec<_> = self.into_iter().collect();
+        entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+        let mut map = Map::new();
+        for (key, value) in entries {
+            map.insert(&Value::String(key), value.into_lox())
+                .expect("strings are valid map keys");
+        }
+        Value::Map(Rc::new(RefCell::new(map)))
+    }
+}
+
+/// Copies the entries out of a Lox map, whose keys must all be strings.
+impl<T: FromLox> FromLox for HashMap<String, T> {
+    fn from_lox(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Map(map) => map
+                .borrow()
+                .iter()
+                .map(|(key, value)| match key {
+                    Value::String(key) => T::from_lox(value)
+                        .map(|value| (key.clone(), value))
+                        .map_err(|message| format!("entry {:?}: {}", key, message)),
+                    key => Err(format!("expected string keys but got {}", key.type_name())),
+                })
+                .collect(),
+            value => Err(mismatch("map", &value)),
+        }
+    }
+}
+
+/// What a typed native function may return: any [`IntoLox`] value, or a
+/// `Result` whose error becomes a runtime error in the calling script.
+pub trait IntoLoxResult {
+    fn into_lox_result(self) -> Result<Value, String>;
+}
+
+impl<T: IntoLox> IntoLoxResult for T {
+    fn into_lox_result(self) -> Result<Value, String> {
+        Ok(self.into_lox())
+    }
+}
+
+impl<T: IntoLox, E: fmt::Display> IntoLoxResult for Result<T, E> {
+    fn into_lox_result(self) -> Result<Value, String> {
+        self.map(IntoLox::into_lox)
+            .map_err(|error| error.to_string())
+    }
+}
+
+/// A Rust closure with typed parameters that can be registered with
+/// [`Interpreter::register_typed`](crate::Interpreter::register_typed).
+/// Implemented for closures of up to eight arguments, each of which must
+/// implement [`FromLox`]. `Args` is the tuple of parameter types and only
+/// serves to tell the implementations apart.
+pub trait NativeFn<Args> {
+    fn arity(&self) -> usize;
+    fn invoke(&self, arguments: &[Value]) -> Result<Value, String>;
+}
+
+macro_rules! impl_native_fn {
+    ($arity:expr; $($argument:ident),*) => {
+        impl<F, R, $($argument),*> NativeFn<($($argument,)*)> for F
+        where
+            F: Fn($($argument),*) -> R,
+            R: IntoLoxResult,
+            $($argument: FromLox,)*
+        {
+            fn arity(&self) -> usize {
+                $arity
+            }
+
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn invoke(&self, arguments: &[Value]) -> Result<Value, String> {
+                let mut arguments = arguments.iter().cloned().enumerate();
+                $(
+                    let (index, value) = arguments.next().expect("arity is checked before the call");
+                    let $argument = $argument::from_lox(value)
+                        .map_err(|message| format!("Argument {}: {}.", index + 1, message))?;
+                )*
+                (self)($($argument),*).into_lox_result()
+            }
+        }
+    };
+}
+
+impl_native_fn!(0;);
+impl_native_fn!(1; A);
+impl_native_fn!(2; A, B);
+impl_native_fn!(3; A, B, C);
+impl_native_fn!(4; A, B, C, D);
+impl_native_fn!(5; A, B, C, D, E);
+impl_native_fn!(6; A, B, C, D, E, G);
+impl_native_fn!(7; A, B, C, D, E, G, H);
+impl_native_fn!(8; A, B, C, D, E, G, H, I);
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+
+use crate::error::{LoxError, Span};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders errors against the source they came from, in the style of:
+///
+/// ```text
+/// error: Expect ';' after value.
+///  --> script.lox:2:9
+///   |
+/// 2 | print 1 2;
+///   |         ^
+///   = help: ...
+/// ```
+pub struct Renderer<'a> {
+    path: &'a str,
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(path: &'a str, source: &'a str) -> Self {
+        Self {
+            path,
+            source,
+            color: true,
+        }
+    }
+
+    /// Enables or disables ANSI colors, e.g. when output is not a terminal.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// A renderer with the same settings, for another file.
+    pub(crate) fn for_source<'b>(&self, path: &'b str, source: &'b str) -> Renderer<'b> {
+        Renderer {
+            path,
+            source,
+            color: self.color,
+        }
+    }
+
+    pub fn render(&self, error: &LoxError) -> String {
+        let span = error.span();
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let (red, blue, bold, reset) = if self.color {
+            (RED, BLUE, BOLD, RESET)
+        } else {
+            ("", "", "", "")
+        };
+
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "{red}{} error{reset}{bold}: {}{reset}",
+            error.kind().to_lowercase(),
+            error.message()
+        );
+        let _ = write!(
+            output,
+            "{gutter}{blue}-->{reset} {}:{}:{}",
+            self.path, span.line, span.column
+        );
+        // Without the line the error is on, the location has to do.
+        if let Some((line, padding, width)) = self.locate(&span) {
+            let _ = write!(output, "\n{gutter} {blue}|{reset}");
+            let _ = write!(output, "\n{blue}{number} |{reset} {line}");
+            let _ = write!(
+                output,
+                "\n{gutter} {blue}|{reset} {padding}{red}{}{reset}",
+                "^".repeat(width)
+            );
+        }
+        if !error.backtrace().is_empty() {
+            let _ = write!(output, "\n{gutter} {blue}={reset} {bold}backtrace{reset}:");
+            for line in error.backtrace_lines() {
+                let _ = write!(output, "\n{gutter}     {line}");
+            }
+        }
+        if let Some(help) = error.help() {
+            let _ = write!(
+                output,
+                "\n{gutter} {blue}={reset} {bold}help{reset}: {help}"
+            );
+        }
+
+        output
+    }
+
+    /// The line `span` starts on, the whitespace before it on that line, and
+    /// how many characters to underline. `None` if the span doesn't fit the
+    /// source, as happens when it points into another one.
+    fn locate(&self, span: &Span) -> Option<(&'a str, String, usize)> {
+        let before = self.source.get(..span.offset)?;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[span.offset..]
+            .find('\n')
+            .map_or(self.source.len(), |i| span.offset + i);
+        let line = self.source[line_start..line_end].trim_end_matches('\r');
+
+        let padding = before[line_start..]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underlined_end = (span.offset + span.length).min(line_start + line.len());
+        let width = self
+            .source
+            .get(span.offset.min(underlined_end)..underlined_end)?
+            .chars()
+            .count()
+            .max(1);
+
+        Some((line, padding, width))
+    }
+}
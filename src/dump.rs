@@ -0,0 +1,23 @@
+//! Readable dumps of the stages a script goes through before it runs, for
+//! debugging scripts and the interpreter itself.
+
+use crate::{error::LoxError, parser::Parser, scanner::Scanner};
+
+/// The tokens `source` scans into, one per line, with their positions.
+pub fn tokens(source: &str) -> Result<String, Vec<LoxError>> {
+    let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+
+    Ok(tokens.iter().map(|token| format!("{}\n", token)).collect())
+}
+
+/// The syntax tree `source` parses into, one top-level statement per line,
+/// as S-expressions. `for` loops show up desugared into `while` loops.
+pub fn ast(source: &str) -> Result<String, Vec<LoxError>> {
+    let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+    let statements = Parser::new(tokens).parse()?;
+
+    Ok(statements
+        .iter()
+        .map(|statement| format!("{}\n", statement))
+        .collect())
+}
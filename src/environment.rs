@@ -1,33 +1,63 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::types::{Token, Value};
+use crate::{
+    error::LoxError,
+    types::{Token, Value},
+};
 
+/// A scope of variables. The global scope looks variables up by name, while
+/// local scopes store their values in `slots`, in declaration order, at the
+/// indices computed by the resolver.
+///
+/// Every script and module has a global scope of its own, which falls back to
+/// a shared scope of `builtins` for names it doesn't define. Assigning to a
+/// builtin defines it in the global scope instead.
 #[derive(Debug, Clone)]
 pub struct Environment {
     enclosing: Option<Rc<RefCell<Environment>>>,
+    builtins: Option<Rc<RefCell<Environment>>>,
     values: HashMap<String, Value>,
+    slots: Vec<Value>,
 }
 
 impl Environment {
     pub fn new() -> Rc<RefCell<Self>> {
         Rc::new(RefCell::new(Self {
             enclosing: None,
+            builtins: None,
             values: HashMap::new(),
+            slots: Vec::new(),
+        }))
+    }
+
+    /// A global scope that can also see the names defined in `builtins`.
+    pub fn new_global(builtins: &Rc<RefCell<Environment>>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            enclosing: None,
+            builtins: Some(builtins.clone()),
+            values: HashMap::new(),
+            slots: Vec::new(),
         }))
     }
 
     pub fn new_enclosed(enclosing: &Rc<RefCell<Environment>>) -> Rc<RefCell<Self>> {
         Rc::new(RefCell::new(Self {
             enclosing: Some(enclosing.clone()),
+            builtins: None,
             values: HashMap::new(),
+            slots: Vec::new(),
         }))
     }
 
     pub fn define(&mut self, name: String, value: Value) {
-        self.values.insert(name, value);
+        if self.enclosing.is_some() {
+            self.slots.push(value);
+        } else {
+            self.values.insert(name, value);
+        }
     }
 
-    pub fn get(&self, name: &Token) -> Result<Value, String> {
+    pub fn get(&self, name: &Token) -> Result<Value, LoxError> {
         if let Some(value) = self.values.get(&name.lexeme) {
             return Ok(value.clone());
         }
@@ -36,10 +66,42 @@ impl Environment {
             return enclosing.borrow().get(name).clone();
         }
 
-        Err(format!("Undefined variable '{}'.", name.lexeme))
+        if let Some(builtins) = &self.builtins {
+            return builtins.borrow().get(name);
+        }
+
+        Err(LoxError::runtime(
+            name,
+            format!("Undefined variable '{}'.", name.lexeme),
+        ))
+    }
+
+    /// Looks up a global by name, without reporting an error.
+    pub fn value(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.builtins.as_ref()?.borrow().value(name),
+        }
+    }
+
+    /// Looks up a global defined in this scope itself, ignoring builtins.
+    pub fn own_value(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
     }
 
-    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), String> {
+    pub fn get_at(&self, depth: usize, index: usize) -> Value {
+        if depth == 0 {
+            return self.slots[index].clone();
+        }
+
+        self.enclosing
+            .as_ref()
+            .expect("resolved depth exceeds environment chain")
+            .borrow()
+            .get_at(depth - 1, index)
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), LoxError> {
         if self.values.contains_key(&name.lexeme) {
             self.values.insert(name.lexeme.clone(), value);
             return Ok(());
@@ -49,6 +111,33 @@ impl Environment {
             return enclosing.borrow_mut().assign(name, value);
         }
 
-        Err(format!("Undefined variable '{}'.", name.lexeme))
+        // Builtins are shared by every module, so assigning to one shadows it
+        // in this global scope rather than changing it for all of them.
+        if self
+            .builtins
+            .as_ref()
+            .is_some_and(|builtins| builtins.borrow().values.contains_key(&name.lexeme))
+        {
+            self.values.insert(name.lexeme.clone(), value);
+            return Ok(());
+        }
+
+        Err(LoxError::runtime(
+            name,
+            format!("Undefined variable '{}'.", name.lexeme),
+        ))
+    }
+
+    pub fn assign_at(&mut self, depth: usize, index: usize, value: Value) {
+        if depth == 0 {
+            self.slots[index] = value;
+            return;
+        }
+
+        self.enclosing
+            .as_ref()
+            .expect("resolved depth exceeds environment chain")
+            .borrow_mut()
+            .assign_at(depth - 1, index, value)
     }
 }
@@ -0,0 +1,315 @@
+use std::{fmt, rc::Rc};
+
+use crate::types::{Token, Value};
+
+/// Region of the source an error points at. `line` and `column` are 1-based
+/// and meant for people; `offset` and `length` are in bytes and meant for
+/// slicing the source when rendering a snippet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+    pub length: usize,
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Self {
+        Self {
+            line: token.line,
+            column: token.column,
+            offset: token.offset,
+            length: token.lexeme.len(),
+        }
+    }
+}
+
+/// Entries shown at each end of a backtrace too long to show in full.
+const BACKTRACE_ENDS: usize = 10;
+
+/// One entry of a runtime backtrace: the function that was executing, or
+/// `None` for top-level script code, and the line it had reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: Option<String>,
+    pub line: usize,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.function {
+            Some(function) => write!(f, "[line {}] in {}()", self.line, function),
+            None => write!(f, "[line {}] in script", self.line),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum LoxError {
+    Scan {
+        message: String,
+        span: Span,
+        help: Option<String>,
+        file: Option<Rc<str>>,
+    },
+    Parse {
+        message: String,
+        span: Span,
+        help: Option<String>,
+        file: Option<Rc<str>>,
+    },
+    Resolve {
+        message: String,
+        span: Span,
+        help: Option<String>,
+        file: Option<Rc<str>>,
+    },
+    Runtime(Box<RuntimeError>),
+}
+
+/// Details of a [`LoxError::Runtime`]. Boxed, as runtime errors unwind
+/// through every nested `evaluate` call and a large error would make each of
+/// their stack frames larger.
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+    pub file: Option<Rc<str>>,
+    pub backtrace: Vec<Frame>,
+    /// The value of a `throw` statement, for errors raised by the script
+    /// itself rather than by the interpreter.
+    pub thrown: Option<Value>,
+    /// Set on errors a script can't catch, such as exceeding the limits its
+    /// host imposed.
+    pub fatal: bool,
+    /// The status a script passed to `exit`, which ends it through this
+    /// error so that the host decides what to do.
+    pub exit: Option<i32>,
+}
+
+impl LoxError {
+    pub fn parse(token: &Token, message: impl Into<String>) -> Self {
+        LoxError::Parse {
+            message: message.into(),
+            span: token.into(),
+            help: None,
+            file: token.file.clone(),
+        }
+    }
+
+    pub fn resolve(token: &Token, message: impl Into<String>) -> Self {
+        LoxError::Resolve {
+            message: message.into(),
+            span: token.into(),
+            help: None,
+            file: token.file.clone(),
+        }
+    }
+
+    pub fn runtime(token: &Token, message: impl Into<String>) -> Self {
+        LoxError::Runtime(Box::new(RuntimeError {
+            message: message.into(),
+            span: token.into(),
+            help: None,
+            file: token.file.clone(),
+            backtrace: Vec::new(),
+            thrown: None,
+            fatal: false,
+            exit: None,
+        }))
+    }
+
+    /// An error raised by `throw value`, described by `message` in case
+    /// nothing catches it.
+    pub(crate) fn throw(token: &Token, message: impl Into<String>, value: Value) -> Self {
+        let mut error = LoxError::runtime(token, message);
+        if let LoxError::Runtime(runtime) = &mut error {
+            runtime.thrown = Some(value);
+        }
+        error
+    }
+
+    /// A runtime error that `try` can't catch.
+    pub(crate) fn fatal(token: &Token, message: impl Into<String>) -> Self {
+        let mut error = LoxError::runtime(token, message);
+        if let LoxError::Runtime(runtime) = &mut error {
+            runtime.fatal = true;
+        }
+        error
+    }
+
+    /// The error a call to `exit(status)` ends the script with.
+    pub(crate) fn exit(token: &Token, status: i32) -> Self {
+        let mut error = LoxError::fatal(token, format!("Script exited with status {}.", status));
+        if let LoxError::Runtime(runtime) = &mut error {
+            runtime.exit = Some(status);
+        }
+        error
+    }
+
+    /// Attaches a note suggesting how to fix the error.
+    pub fn with_help(mut self, note: impl Into<String>) -> Self {
+        match &mut self {
+            LoxError::Scan { help, .. }
+            | LoxError::Parse { help, .. }
+            | LoxError::Resolve { help, .. } => *help = Some(note.into()),
+            LoxError::Runtime(runtime) => runtime.help = Some(note.into()),
+        }
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            LoxError::Scan { message, .. }
+            | LoxError::Parse { message, .. }
+            | LoxError::Resolve { message, .. } => message,
+            LoxError::Runtime(runtime) => &runtime.message,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            LoxError::Scan { span, .. }
+            | LoxError::Parse { span, .. }
+            | LoxError::Resolve { span, .. } => *span,
+            LoxError::Runtime(runtime) => runtime.span,
+        }
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        match self {
+            LoxError::Scan { help, .. }
+            | LoxError::Parse { help, .. }
+            | LoxError::Resolve { help, .. } => help.as_deref(),
+            LoxError::Runtime(runtime) => runtime.help.as_deref(),
+        }
+    }
+
+    /// Path of the file the error is in, or `None` for code that wasn't
+    /// loaded from a file by the interpreter, like the main script passed to
+    /// [`Interpreter::parse_and_run`](crate::Interpreter::parse_and_run).
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            LoxError::Scan { file, .. }
+            | LoxError::Parse { file, .. }
+            | LoxError::Resolve { file, .. } => file.as_deref(),
+            LoxError::Runtime(runtime) => runtime.file.as_deref(),
+        }
+    }
+
+    /// Innermost-first call stack at the point a runtime error was raised.
+    /// Empty for compile errors and for errors raised in top-level code.
+    pub fn backtrace(&self) -> &[Frame] {
+        match self {
+            LoxError::Runtime(runtime) => &runtime.backtrace,
+            _ => &[],
+        }
+    }
+
+    /// The backtrace as it is shown to people, one line per entry. A frame
+    /// that recursion repeats is shown once, followed by how many more calls
+    /// there were, and if that still leaves more than `BACKTRACE_ENDS` at
+    /// each end, the middle is left out.
+    pub(crate) fn backtrace_lines(&self) -> Vec<String> {
+        let mut runs: Vec<(&Frame, usize)> = Vec::new();
+        for frame in self.backtrace() {
+            match runs.last_mut() {
+                Some((last, count)) if *last == frame => *count += 1,
+                _ => runs.push((frame, 1)),
+            }
+        }
+
+        let mut lines = Vec::new();
+        let omitted = runs.len().saturating_sub(2 * BACKTRACE_ENDS);
+        for (i, &(frame, count)) in runs.iter().enumerate() {
+            if omitted > 0 && i == BACKTRACE_ENDS {
+                let calls: usize = runs[i..i + omitted].iter().map(|(_, count)| count).sum();
+                lines.push(format!("... {}", more_calls(calls)));
+            }
+            if omitted > 0 && (BACKTRACE_ENDS..BACKTRACE_ENDS + omitted).contains(&i) {
+                continue;
+            }
+
+            lines.push(frame.to_string());
+            if count > 1 {
+                let name = frame.function.as_deref().unwrap_or("script");
+                lines.push(format!("... {} to {}()", more_calls(count - 1), name));
+            }
+        }
+        lines
+    }
+
+    /// The value a script passed to `throw`, if the error came from an
+    /// uncaught `throw` statement.
+    pub fn thrown(&self) -> Option<&Value> {
+        match self {
+            LoxError::Runtime(runtime) => runtime.thrown.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The status the script asked to exit with, if it ended by calling
+    /// `exit`. That isn't a failure, so hosts usually don't report it.
+    pub fn exit_status(&self) -> Option<i32> {
+        match self {
+            LoxError::Runtime(runtime) => runtime.exit,
+            _ => None,
+        }
+    }
+
+    /// Whether a `try` statement may catch the error. Compile errors in
+    /// imported modules can be caught like runtime errors.
+    pub(crate) fn is_catchable(&self) -> bool {
+        !matches!(self, LoxError::Runtime(runtime) if runtime.fatal)
+    }
+
+    /// Process exit status for this error, following the sysexits.h codes
+    /// used by the reference implementation: 65 (EX_DATAERR) when the script
+    /// could not be compiled and 70 (EX_SOFTWARE) when it failed at runtime.
+    /// Scripts that called `exit` get the status they asked for.
+    pub fn exit_code(&self) -> i32 {
+        if let Some(status) = self.exit_status() {
+            return status;
+        }
+
+        match self {
+            LoxError::Scan { .. } | LoxError::Parse { .. } | LoxError::Resolve { .. } => 65,
+            LoxError::Runtime(_) => 70,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LoxError::Scan { .. } => "Scan",
+            LoxError::Parse { .. } => "Parse",
+            LoxError::Resolve { .. } => "Resolve",
+            LoxError::Runtime(_) => "Runtime",
+        }
+    }
+}
+
+fn more_calls(count: usize) -> String {
+    match count {
+        1 => String::from("1 more call"),
+        count => format!("{} more calls", count),
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        match self.file() {
+            Some(file) => write!(f, "[{} line {}:{}]", file, span.line, span.column)?,
+            None => write!(f, "[line {}:{}]", span.line, span.column)?,
+        }
+        write!(f, " {} error: {}", self.kind(), self.message())?;
+        for line in self.backtrace_lines() {
+            write!(f, "\n{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoxError {}
@@ -1,47 +1,724 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use crate::{
+    convert::NativeFn,
+    diagnostic::Renderer,
     environment::Environment,
-    types::{NativeFunction, Stmt, Value},
+    error::{Frame, LoxError},
+    output::Output,
+    parser::Parser,
+    resolver::Resolver,
+    scanner::Scanner,
+    stack,
+    types::{
+        Arity, Class, ControlFlow, HostFunction, Instance, Module, NativeFunction, Stmt, Token,
+        TokenType, Value,
+    },
 };
 
+/// Variations of the language semantics an [`Interpreter`] can run with.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Dialect {
+    /// Standard Lox semantics: only `nil` and `false` are falsey.
+    #[default]
+    Lox,
+    /// Opt-in compatibility mode where `0` and `""` are falsey as well, as
+    /// they were in earlier versions of rubrs. Affects `if`, `while`, `for`,
+    /// `and`, `or` and `!`.
+    Permissive,
+}
+
+/// What scripts run by an [`Interpreter`] may access through native
+/// functions. Nothing is allowed by default. Native functions needing a
+/// capability that isn't granted aren't defined, and scripts using one get an
+/// error naming the capability.
+///
+/// ```
+/// use rubrs::{Capabilities, Interpreter};
+///
+/// let interpreter = Interpreter::new();
+/// let errors = interpreter.eval("clock()").unwrap_err();
+/// assert_eq!(errors[0].message(), "Can't use 'clock': clock access is disabled.");
+///
+/// let interpreter = Interpreter::builder()
+///     .capabilities(Capabilities::all())
+///     .build();
+/// assert!(interpreter.eval("clock()").is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capabilities {
+    /// `readFile` and `writeFile`.
+    pub filesystem: bool,
+    /// `getenv`.
+    pub environment: bool,
+    /// `clock`.
+    pub clock: bool,
+    /// `exit`, which ends the script with an error carrying the status; see
+    /// [`LoxError::exit_status`].
+    pub process: bool,
+    /// `readLine`.
+    pub stdin: bool,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Self {
+            filesystem: true,
+            environment: true,
+            clock: true,
+            process: true,
+            stdin: true,
+        }
+    }
+
+    /// Only pure computation: no access to anything outside the script.
+    pub fn none() -> Self {
+        Self {
+            filesystem: false,
+            environment: false,
+            clock: false,
+            process: false,
+            stdin: false,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Runs Lox code. Global variables persist between calls, so an interpreter
+/// can be fed a script piece by piece, as the REPL does.
 #[derive(Debug, Clone)]
 pub struct Interpreter {
-    #[allow(unused)]
+    /// Native functions and values defined by the host, visible from the
+    /// script and from every module it imports.
+    builtins: Rc<RefCell<Environment>>,
     globals: Rc<RefCell<Environment>>,
-    environment: Rc<RefCell<Environment>>,
+    /// Imported modules by canonical path, so each one only runs once.
+    modules: RefCell<HashMap<PathBuf, ModuleState>>,
+    /// The chain of files currently being run, innermost last, for
+    /// reporting import cycles.
+    loading: RefCell<Vec<(PathBuf, Rc<str>)>>,
+    /// Source of every file loaded so far, for rendering errors in them.
+    sources: RefCell<HashMap<Rc<str>, Rc<str>>>,
+    /// Class of the error values `catch` receives and `Error()` creates.
+    error_class: Rc<Class>,
+    frames: RefCell<Vec<CallFrame>>,
+    dialect: Dialect,
+    capabilities: Capabilities,
+    output: Output,
+    error_output: Output,
+    limits: Limits,
+    steps: Cell<u64>,
+    deadline: Cell<Option<Instant>>,
 }
 
-impl Interpreter {
-    pub fn new() -> Self {
-        let globals = Environment::new();
-        let environment = Environment::new_enclosed(&globals);
+/// Configures an [`Interpreter`] before it is created.
+///
+/// ```
+/// use rubrs::{Dialect, Interpreter};
+///
+/// let interpreter = Interpreter::builder().dialect(Dialect::Permissive).build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InterpreterBuilder {
+    dialect: Dialect,
+    capabilities: Capabilities,
+    output: Option<Output>,
+    error_output: Option<Output>,
+    limits: Limits,
+}
 
-        globals.borrow_mut().define(
-            "clock".to_string(),
-            Value::NativeFunction(NativeFunction::Clock),
-        );
+/// Bounds on how much work a single [`Interpreter::parse_and_run`] or
+/// [`Interpreter::eval`] call may do, so that a runaway script fails with a
+/// runtime error instead of hanging or crashing the host. The script can't
+/// catch these errors with `try`.
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    max_call_depth: usize,
+    max_steps: Option<u64>,
+    timeout: Option<Duration>,
+}
 
+impl Default for Limits {
+    fn default() -> Self {
         Self {
-            globals,
-            environment,
+            // A Lox call takes about 13KB of stack in a debug build and 3.6KB
+            // in a release build. This many fit on the 8MB main thread stack
+            // in both; the 2MB stack of a spawned thread holds nearly as many
+            // in release.
+            max_call_depth: 512,
+            max_steps: None,
+            timeout: None,
+        }
+    }
+}
+
+impl InterpreterBuilder {
+    /// Runs scripts with the given dialect instead of standard Lox.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Restricts what scripts may access through native functions.
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Maximum number of nested function calls before a script fails with
+    /// "Stack overflow.". Defaults to 512, which the main thread has room for
+    /// in debug and release builds. Scripts also fail with that error,
+    /// instead of crashing, if they run out of stack first: on a smaller
+    /// thread stack, in a debug build, or when deeply nested code is parsed.
+    ///
+    /// ```
+    /// use rubrs::Interpreter;
+    ///
+    /// let interpreter = Interpreter::builder().max_call_depth(10).build();
+    /// interpreter.eval("fun f(n) { if (n > 0) f(n - 1); }").unwrap();
+    /// assert!(interpreter.eval("f(9)").is_ok());
+    /// let errors = interpreter.eval("f(10)").unwrap_err();
+    /// assert_eq!(errors[0].message(), "Stack overflow.");
+    /// ```
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.limits.max_call_depth = depth;
+        self
+    }
+
+    /// Maximum number of statements a script may execute per run. Unlimited
+    /// by default.
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.limits.max_steps = Some(steps);
+        self
+    }
+
+    /// Wall-clock time a script may run for. Unlimited by default.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use rubrs::Interpreter;
+    ///
+    /// let interpreter = Interpreter::builder()
+    ///     .timeout(Duration::from_millis(10))
+    ///     .build();
+    /// let errors = interpreter.parse_and_run("while (true) {}").unwrap_err();
+    /// assert_eq!(errors[0].message(), "Execution timed out.");
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.limits.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the output of `print` somewhere other than stdout.
+    pub fn output(mut self, output: Output) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Sends error reports from [`Interpreter::report`] somewhere other than
+    /// stderr.
+    pub fn error_output(mut self, output: Output) -> Self {
+        self.error_output = Some(output);
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let builtins = Environment::new();
+
+        for native in NativeFunction::ALL {
+            if !native.is_allowed(&self.capabilities) {
+                continue;
+            }
+
+            builtins
+                .borrow_mut()
+                .define(native.name().to_string(), Value::NativeFunction(native));
+        }
+
+        Interpreter {
+            globals: Environment::new_global(&builtins),
+            builtins,
+            modules: RefCell::new(HashMap::new()),
+            loading: RefCell::new(Vec::new()),
+            sources: RefCell::new(HashMap::new()),
+            error_class: Class::builtin("Error"),
+            frames: RefCell::new(Vec::new()),
+            dialect: self.dialect,
+            capabilities: self.capabilities,
+            output: self.output.unwrap_or_else(Output::stdout),
+            error_output: self.error_output.unwrap_or_else(Output::stderr),
+            limits: self.limits,
+            steps: Cell::new(0),
+            deadline: Cell::new(None),
         }
     }
+}
 
-    pub fn parse_and_run(&self, code: &str) -> Result<(), String> {
-        let mut scanner = crate::scanner::Scanner::new(code.to_string());
-        let tokens = scanner.scan_tokens();
-        let mut parser = crate::parser::Parser::new(tokens);
+#[derive(Debug, Clone)]
+enum ModuleState {
+    /// The module's code is running, so importing it again is a cycle.
+    Loading,
+    Loaded(Rc<Module>),
+}
+
+/// A function call in progress, with the line it was called from.
+#[derive(Debug, Clone)]
+struct CallFrame {
+    function: String,
+    line: usize,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
 
-        self.run(parser.parse()?)?;
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
+    /// Runs a whole script. Modules it imports are looked up relative to the
+    /// current directory.
+    pub fn parse_and_run(&self, code: &str) -> Result<(), Vec<LoxError>> {
+        let statements = self.compile(code, None, false)?;
+
+        self.start_run();
+        self.run(statements, &self.globals)
+            .map_err(|error| vec![error])?;
         Ok(())
     }
 
-    pub fn run(&self, statements: Vec<Stmt>) -> Result<(), String> {
+    /// Runs a snippet like [`parse_and_run`](Self::parse_and_run), but keeps
+    /// its source under `name` so that errors in it can be rendered against
+    /// it later: a function declared in one REPL line can fail when another
+    /// line calls it. Modules it imports are looked up relative to the
+    /// current directory.
+    pub fn parse_and_run_named(&self, name: &str, code: &str) -> Result<(), Vec<LoxError>> {
+        let file: Rc<str> = Rc::from(name);
+        self.sources
+            .borrow_mut()
+            .insert(file.clone(), Rc::from(code));
+        let statements = self.compile(code, Some(file), false)?;
+
+        self.start_run();
+        self.run(statements, &self.globals)
+            .map_err(|error| vec![error])?;
+        Ok(())
+    }
+
+    /// Runs a whole script that was read from `path`. Modules it imports are
+    /// looked up relative to the script's directory, and errors record the
+    /// path they occurred in.
+    pub fn parse_and_run_at(&self, path: &str, code: &str) -> Result<(), Vec<LoxError>> {
+        let file: Rc<str> = Rc::from(path);
+        self.sources
+            .borrow_mut()
+            .insert(file.clone(), Rc::from(code));
+        let statements = self.compile(code, Some(file.clone()), false)?;
+
+        // The script counts as loading while it runs, so a module importing
+        // it back is reported as a cycle.
+        let key = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+        self.modules
+            .borrow_mut()
+            .insert(key.clone(), ModuleState::Loading);
+        self.loading.borrow_mut().push((key.clone(), file));
+
+        self.start_run();
+        let result = self.run(statements, &self.globals);
+
+        self.loading.borrow_mut().pop();
+        self.modules.borrow_mut().remove(&key);
+        result.map_err(|error| vec![error])
+    }
+
+    /// Runs a snippet and returns the value of its final expression statement,
+    /// or `nil` if it doesn't end with one. The semicolon after the final
+    /// expression is optional.
+    ///
+    /// ```
+    /// use rubrs::{Interpreter, Value};
+    ///
+    /// let interpreter = Interpreter::new();
+    /// interpreter.eval("var greeting = \"hello\";").unwrap();
+    /// let value = interpreter.eval("greeting + \" world\"").unwrap();
+    /// assert_eq!(value, Value::String(String::from("hello world")));
+    /// ```
+    pub fn eval(&self, code: &str) -> Result<Value, Vec<LoxError>> {
+        let mut statements = self.compile(code, None, true)?;
+
+        let last = match statements.last() {
+            Some(Stmt::Expression(_)) => statements.pop(),
+            _ => None,
+        };
+
+        self.start_run();
+        self.run(statements, &self.globals)
+            .map_err(|error| vec![error])?;
+
+        match last {
+            Some(Stmt::Expression(expr)) => expr
+                .evaluate(self, &self.globals)
+                .map_err(|error| vec![error]),
+            _ => Ok(Value::Nil),
+        }
+    }
+
+    /// Reads a global variable.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().value(name)
+    }
+
+    /// Defines, or redefines, a global variable visible to scripts and to the
+    /// modules they import.
+    pub fn define(&self, name: &str, value: Value) {
+        self.builtins.borrow_mut().define(name.to_string(), value);
+    }
+
+    /// Exposes a Rust closure to scripts as a global function. Arguments are
+    /// checked against `arity` before the closure runs, and an `Err` it
+    /// returns becomes a runtime error at the call site.
+    ///
+    /// ```
+    /// use rubrs::{Arity, Interpreter, Value};
+    ///
+    /// let interpreter = Interpreter::new();
+    /// interpreter.register_fn("sum", Arity::AtLeast(0), |arguments| {
+    ///     let mut total = 0.0;
+    ///     for argument in arguments {
+    ///         match argument {
+    ///             Value::Number(number) => total += number,
+    ///             _ => return Err(String::from("sum() only adds numbers.")),
+    ///         }
+    ///     }
+    ///     Ok(Value::Number(total))
+    /// });
+    ///
+    /// assert_eq!(interpreter.eval("sum(1, 2, 3)").unwrap(), Value::Number(6.0));
+    /// ```
+    pub fn register_fn<F>(&self, name: &str, arity: impl Into<Arity>, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + 'static,
+    {
+        let function = HostFunction::new(name.to_string(), arity.into(), Box::new(function));
+        self.define(name, Value::HostFunction(Rc::new(function)));
+    }
+
+    /// Like [`register_fn`](Self::register_fn), but for closures with typed
+    /// parameters. The arity comes from the closure's signature and each
+    /// argument is converted with [`FromLox`](crate::FromLox), so a script
+    /// passing the wrong type gets a runtime error instead of the closure
+    /// being called. The closure may return any [`IntoLox`](crate::IntoLox)
+    /// value or a `Result` of one.
+    ///
+    /// ```
+    /// use rubrs::{Interpreter, Value};
+    ///
+    /// let interpreter = Interpreter::new();
+    /// interpreter.register_typed("repeat", |text: String, times: usize| text.repeat(times));
+    ///
+    /// assert_eq!(
+    ///     interpreter.eval("repeat(\"ab\", 3)").unwrap(),
+    ///     Value::String(String::from("ababab"))
+    /// );
+    /// assert!(interpreter.eval("repeat(\"ab\", \"3\")").is_err());
+    /// ```
+    pub fn register_typed<Args, F>(&self, name: &str, function: F)
+    where
+        F: NativeFn<Args> + 'static,
+    {
+        let arity = function.arity();
+        self.register_fn(name, arity, move |arguments| function.invoke(arguments));
+    }
+
+    /// Writes errors returned by [`parse_and_run`](Self::parse_and_run) or
+    /// [`eval`](Self::eval) to the error output, rendered against the code
+    /// that produced them. Errors in imported modules are rendered against
+    /// the module's source rather than `renderer`'s.
+    pub fn report(&self, renderer: &Renderer, errors: &[LoxError]) {
+        for error in errors {
+            let file = error.file().and_then(|file| {
+                let source = self.sources.borrow().get(file).cloned()?;
+                Some((file, source))
+            });
+            let rendered = match file {
+                Some((file, source)) => renderer.for_source(file, &source).render(error),
+                None => renderer.render(error),
+            };
+            self.error_output.emit(&rendered);
+        }
+    }
+
+    /// Loads the module named by the path in an `import` statement, running
+    /// it the first time it is imported. Paths are relative to the directory
+    /// of the importing file.
+    pub(crate) fn import(&self, path: &Token) -> Result<Rc<Module>, LoxError> {
+        let TokenType::String(relative) = &path.token_type else {
+            unreachable!("the parser only accepts string literals as module paths")
+        };
+        if !self.capabilities.filesystem {
+            return Err(LoxError::runtime(
+                path,
+                format!(
+                    "Can't import '{}': filesystem access is disabled.",
+                    relative
+                ),
+            ));
+        }
+
+        let resolved = match path
+            .file
+            .as_deref()
+            .and_then(|file| Path::new(file).parent())
+        {
+            Some(directory) => directory.join(relative),
+            None => PathBuf::from(relative),
+        };
+        let file: Rc<str> = Rc::from(resolved.to_string_lossy().as_ref());
+        let load_error = |error: std::io::Error| {
+            LoxError::runtime(
+                path,
+                format!("Could not load module '{}': {}.", file, error),
+            )
+        };
+
+        let key = fs::canonicalize(&resolved).map_err(load_error)?;
+        let state = self.modules.borrow().get(&key).cloned();
+        match state {
+            Some(ModuleState::Loaded(module)) => return Ok(module),
+            Some(ModuleState::Loading) => {
+                let loading = self.loading.borrow();
+                let start = loading
+                    .iter()
+                    .position(|(loading_key, _)| *loading_key == key)
+                    .unwrap_or(0);
+                let mut cycle: Vec<&str> = loading[start..]
+                    .iter()
+                    .map(|(_, file)| file.as_ref())
+                    .collect();
+                cycle.push(&file);
+                return Err(LoxError::runtime(
+                    path,
+                    format!("Circular import: {}.", cycle.join(" -> ")),
+                ));
+            }
+            None => {}
+        }
+
+        let source = fs::read_to_string(&resolved).map_err(load_error)?;
+        self.sources
+            .borrow_mut()
+            .insert(file.clone(), Rc::from(source.as_str()));
+        // Only the first compile error of a module makes it out through the
+        // import, as a statement can only fail with one.
+        let statements = self
+            .compile(&source, Some(file.clone()), false)
+            .map_err(|errors| errors.into_iter().next().expect("errors are never empty"))?;
+
+        self.modules
+            .borrow_mut()
+            .insert(key.clone(), ModuleState::Loading);
+        self.loading.borrow_mut().push((key.clone(), file.clone()));
+
+        let globals = Environment::new_global(&self.builtins);
+        let result = self.run(statements, &globals);
+
+        self.loading.borrow_mut().pop();
+        match result {
+            Ok(()) => {
+                let module = Rc::new(Module::new(file, globals));
+                self.modules
+                    .borrow_mut()
+                    .insert(key, ModuleState::Loaded(module.clone()));
+                Ok(module)
+            }
+            Err(error) => {
+                self.modules.borrow_mut().remove(&key);
+                Err(error)
+            }
+        }
+    }
+
+    fn compile(
+        &self,
+        code: &str,
+        file: Option<Rc<str>>,
+        trailing_expression: bool,
+    ) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let mut scanner = Scanner::new(code.to_string()).in_file(file);
+        let tokens = scanner.scan_tokens()?;
+        let mut parser = Parser::new(tokens).allow_trailing_expression(trailing_expression);
+
+        let mut statements = parser.parse()?;
+        Resolver::new().resolve(&mut statements)?;
+
+        Ok(statements)
+    }
+
+    /// Runs top-level statements in the global scope `globals`.
+    fn run(
+        &self,
+        statements: Vec<Stmt>,
+        globals: &Rc<RefCell<Environment>>,
+    ) -> Result<(), LoxError> {
         for statement in statements {
-            statement.evaluate(&self.environment)?
+            match statement.evaluate(self, globals) {
+                Ok(()) => {}
+                Err(ControlFlow::Return(_)) => {
+                    unreachable!("the resolver rejects top-level 'return'")
+                }
+                Err(ControlFlow::Break | ControlFlow::Continue) => {
+                    unreachable!("the resolver rejects 'break' and 'continue' outside loops")
+                }
+                Err(ControlFlow::Error(error)) => return Err(error),
+            }
         }
 
         Ok(())
     }
+
+    /// An error value with the given `message` and `line` fields.
+    pub(crate) fn error_value(&self, message: &str, line: usize) -> Value {
+        Instance::with_fields(
+            &self.error_class,
+            [
+                ("message", Value::String(message.to_string())),
+                ("line", Value::Number(line as f64)),
+            ],
+        )
+    }
+
+    /// The value a `catch` clause receives for `error`: whatever was thrown,
+    /// or an error value describing an error raised by the interpreter. Fails
+    /// with `error` itself if it can't be caught.
+    pub(crate) fn catch(&self, error: LoxError) -> Result<Value, LoxError> {
+        if !error.is_catchable() {
+            return Err(error);
+        }
+
+        Ok(match error.thrown() {
+            Some(value) => value.clone(),
+            None => self.error_value(error.message(), error.span().line),
+        })
+    }
+
+    pub(crate) fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub(crate) fn print(&self, value: &Value) {
+        self.output.emit(&value.to_string());
+    }
+
+    pub(crate) fn is_truthy(&self, value: &Value) -> bool {
+        value.is_truthy(self.dialect)
+    }
+
+    /// Resets the step count and deadline at the start of a top-level run.
+    fn start_run(&self) {
+        self.steps.set(0);
+        self.deadline
+            .set(self.limits.timeout.map(|timeout| Instant::now() + timeout));
+    }
+
+    pub(crate) fn count_step(&self) {
+        self.steps.set(self.steps.get() + 1);
+    }
+
+    /// Fails once the step budget or deadline has been used up. Checked on
+    /// every loop iteration and function call, as those are the only ways a
+    /// script can keep running.
+    pub(crate) fn check_limits(&self, token: &Token) -> Result<(), LoxError> {
+        if let Some(max_steps) = self.limits.max_steps {
+            if self.steps.get() > max_steps {
+                return Err(
+                    LoxError::fatal(token, "Step limit exceeded.").with_help(format!(
+                        "the interpreter is limited to {} steps per run",
+                        max_steps
+                    )),
+                );
+            }
+        }
+
+        if let Some(deadline) = self.deadline.get() {
+            if Instant::now() >= deadline {
+                return Err(LoxError::fatal(token, "Execution timed out."));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails once the thread is about to run out of stack. Checked as each
+    /// expression is evaluated, since the interpreter recurses through them.
+    pub(crate) fn check_stack(&self, token: &Token) -> Result<(), LoxError> {
+        if stack::exhausted() {
+            return Err(LoxError::fatal(token, "Stack overflow.")
+                .with_help("the script nests too deeply for the stack of the thread running it"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn push_frame(&self, function: String, paren: &Token) -> Result<(), LoxError> {
+        let mut frames = self.frames.borrow_mut();
+        if frames.len() >= self.limits.max_call_depth {
+            return Err(LoxError::fatal(paren, "Stack overflow."));
+        }
+
+        frames.push(CallFrame {
+            function,
+            line: paren.line,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn pop_frame(&self) {
+        self.frames.borrow_mut().pop();
+    }
+
+    /// Records the current call stack on a runtime error that doesn't have
+    /// one yet. Called as the error leaves each function, so the innermost
+    /// call is the one that gets to capture the full stack.
+    pub(crate) fn attach_backtrace(&self, mut error: LoxError) -> LoxError {
+        if let LoxError::Runtime(runtime) = &mut error {
+            if !runtime.backtrace.is_empty() {
+                return error;
+            }
+
+            let frames = self.frames.borrow();
+            let mut line = runtime.span.line;
+            for frame in frames.iter().rev() {
+                runtime.backtrace.push(Frame {
+                    function: Some(frame.function.clone()),
+                    line,
+                });
+                line = frame.line;
+            }
+            runtime.backtrace.push(Frame {
+                function: None,
+                line,
+            });
+        }
+
+        error
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
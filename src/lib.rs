@@ -0,0 +1,37 @@
+//! A tree-walking interpreter for the Lox language that can be used from the
+//! command line or embedded in a Rust program as a scripting engine.
+//!
+//! ```
+//! use rubrs::{Interpreter, Value};
+//!
+//! let interpreter = Interpreter::new();
+//! interpreter
+//!     .parse_and_run("fun square(n) { return n * n; }")
+//!     .unwrap();
+//!
+//! assert_eq!(interpreter.eval("square(4)").unwrap(), Value::Number(16.0));
+//! ```
+
+mod convert;
+mod diagnostic;
+pub mod dump;
+mod environment;
+mod error;
+mod interpreter;
+mod list;
+mod map;
+mod output;
+mod parser;
+mod resolver;
+mod scanner;
+mod stack;
+mod types;
+
+pub use convert::{FromLox, IntoLox, IntoLoxResult, NativeFn};
+pub use diagnostic::Renderer;
+pub use error::{Frame, LoxError, RuntimeError, Span};
+pub use interpreter::{Capabilities, Dialect, Interpreter, InterpreterBuilder};
+pub use list::List;
+pub use map::Map;
+pub use output::Output;
+pub use types::{Arity, Class, Function, HostFunction, Instance, Module, NativeFunction, Value};
@@ -0,0 +1,45 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::types::{self, Value};
+
+/// The contents of a Lox list: a `Vec` of its elements, which it derefs to.
+#[derive(Debug, Clone, Default)]
+pub struct List(Vec<Value>);
+
+impl List {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl From<Vec<Value>> for List {
+    fn from(elements: Vec<Value>) -> Self {
+        Self(elements)
+    }
+}
+
+impl FromIterator<Value> for List {
+    fn from_iter<I: IntoIterator<Item = Value>>(elements: I) -> Self {
+        Self(elements.into_iter().collect())
+    }
+}
+
+impl Deref for List {
+    type Target = Vec<Value>;
+
+    fn deref(&self) -> &Vec<Value> {
+        &self.0
+    }
+}
+
+impl DerefMut for List {
+    fn deref_mut(&mut self) -> &mut Vec<Value> {
+        &mut self.0
+    }
+}
+
+impl Drop for List {
+    fn drop(&mut self) {
+        types::drop_values(std::mem::take(&mut self.0));
+    }
+}
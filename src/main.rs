@@ -1,37 +1,85 @@
-use interpreter::Interpreter;
+use std::io::IsTerminal;
+
+use rubrs::{dump, Capabilities, Dialect, Interpreter, LoxError, Renderer};
 use rustyline::{error::ReadlineError, DefaultEditor};
 
-mod environment;
-mod interpreter;
-mod parser;
-mod scanner;
-mod types;
+/// Stack of the thread scripts run on, so that they can recurse up to
+/// `MAX_CALL_DEPTH` calls deep even in a debug build.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Deeper than the library default, for recursive algorithms over larger
+/// inputs.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// What to do with the script given on the command line.
+enum Mode {
+    Run,
+    DumpTokens,
+    DumpAst,
+}
 
-fn run_file(filename: &str) {
+fn run_file(interpreter: Interpreter, filename: &str) {
     let contents =
         std::fs::read_to_string(filename).expect("Something went wrong reading the file");
-    let interpreter = Interpreter::new();
-    match interpreter.parse_and_run(&contents) {
-        Ok(_) => {}
-        Err(error) => println!("{}", error),
+    if let Err(errors) = interpreter.parse_and_run_at(filename, &contents) {
+        if let Some(status) = errors[0].exit_status() {
+            std::process::exit(status);
+        }
+
+        let renderer = Renderer::new(filename, &contents).color(use_color());
+        interpreter.report(&renderer, &errors);
+        std::process::exit(errors[0].exit_code());
     }
 }
 
-fn repl() {
+/// Prints the tokens or syntax tree of a script instead of running it.
+fn dump_file(
+    interpreter: Interpreter,
+    filename: &str,
+    dump: fn(&str) -> Result<String, Vec<LoxError>>,
+) {
+    let contents =
+        std::fs::read_to_string(filename).expect("Something went wrong reading the file");
+    match dump(&contents) {
+        Ok(output) => print!("{}", output),
+        Err(errors) => {
+            let renderer = Renderer::new(filename, &contents).color(use_color());
+            interpreter.report(&renderer, &errors);
+            std::process::exit(errors[0].exit_code());
+        }
+    }
+}
+
+/// Colors are only used when writing diagnostics to a terminal, and can be
+/// turned off with the `NO_COLOR` convention.
+fn use_color() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn repl(interpreter: Interpreter) {
     let mut rl = DefaultEditor::new().unwrap();
-    let interpreter = Interpreter::new();
 
     if rl.load_history(".history").is_err() {
         println!("No previous history.");
     }
+    let mut number = 0;
     loop {
         match rl.readline("rubrs> ") {
             Ok(line) => {
                 rl.add_history_entry(line.as_str()).unwrap();
 
-                match interpreter.parse_and_run(&line) {
-                    Ok(_) => {}
-                    Err(error) => println!("{}", error),
+                // Every line is a source of its own, so errors raised in code
+                // from an earlier line are shown against that line.
+                number += 1;
+                let name = format!("<repl:{}>", number);
+                if let Err(errors) = interpreter.parse_and_run_named(&name, &line) {
+                    if let Some(status) = errors[0].exit_status() {
+                        rl.save_history(".history").unwrap();
+                        std::process::exit(status);
+                    }
+
+                    let renderer = Renderer::new(&name, &line).color(use_color());
+                    interpreter.report(&renderer, &errors);
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -49,11 +97,42 @@ fn repl() {
 }
 
 fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
+    let cli = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(cli)
+        .expect("Failed to start the interpreter thread");
+    if cli.join().is_err() {
+        // The panic message has already been printed.
+        std::process::exit(101);
+    }
+}
 
-    match args.len() {
-        3.. => println!("Usage: rubrs [script]"),
-        2 => run_file(&args[1]),
-        _ => repl(),
+fn cli() {
+    let mut dialect = Dialect::Lox;
+    let mut mode = Mode::Run;
+    let mut scripts = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--permissive" => dialect = Dialect::Permissive,
+            "--dump-tokens" => mode = Mode::DumpTokens,
+            "--dump-ast" => mode = Mode::DumpAst,
+            _ => scripts.push(arg),
+        }
+    }
+
+    let interpreter = Interpreter::builder()
+        .dialect(dialect)
+        .capabilities(Capabilities::all())
+        .max_call_depth(MAX_CALL_DEPTH)
+        .build();
+    match (mode, scripts.as_slice()) {
+        (Mode::Run, []) => repl(interpreter),
+        (Mode::Run, [script]) => run_file(interpreter, script),
+        (Mode::DumpTokens, [script]) => dump_file(interpreter, script, dump::tokens),
+        (Mode::DumpAst, [script]) => dump_file(interpreter, script, dump::ast),
+        _ => {
+            eprintln!("Usage: rubrs [--permissive] [--dump-tokens | --dump-ast] [script]");
+            std::process::exit(64);
+        }
     }
 }
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use crate::types::{self, Value};
+
+/// The contents of a Lox map. Entries keep the order in which their keys were
+/// first inserted: assigning to an existing key keeps its position, and a key
+/// that is removed and inserted again moves to the end.
+///
+/// Keys must be strings, numbers, booleans or `nil`.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(Key, Value)>,
+    positions: HashMap<Key, usize>,
+}
+
+/// A map key. Numbers are stored by their bits, with `-0` folded into `0` so
+/// that keys equal under `==` hash the same.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Nil,
+    Boolean(bool),
+    Number(u64),
+    String(String),
+}
+
+impl Key {
+    fn new(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Nil => Ok(Key::Nil),
+            Value::Boolean(boolean) => Ok(Key::Boolean(*boolean)),
+            Value::Number(number) if number.is_nan() => Err(String::from("Map keys can't be NaN.")),
+            Value::Number(number) => Ok(Key::Number((number + 0.0).to_bits())),
+            Value::String(string) => Ok(Key::String(string.clone())),
+            value => Err(format!(
+                "Map keys must be strings, numbers, booleans or nil, not {}.",
+                value.type_name()
+            )),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Key::Nil => Value::Nil,
+            Key::Boolean(boolean) => Value::Boolean(*boolean),
+            Key::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            Key::String(string) => Value::String(string.clone()),
+        }
+    }
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Looks up `key`. Fails only if `key` can't be a map key.
+    pub fn get(&self, key: &Value) -> Result<Option<Value>, String> {
+        let key = Key::new(key)?;
+        Ok(self
+            .positions
+            .get(&key)
+            .map(|&position| self.entries[position].1.clone()))
+    }
+
+    pub fn contains_key(&self, key: &Value) -> Result<bool, String> {
+        Ok(self.positions.contains_key(&Key::new(key)?))
+    }
+
+    /// Sets `key` to `value`, returning the value it replaced.
+    pub fn insert(&mut self, key: &Value, value: Value) -> Result<Option<Value>, String> {
+        let key = Key::new(key)?;
+        if let Some(&position) = self.positions.get(&key) {
+            return Ok(Some(std::mem::replace(
+                &mut self.entries[position].1,
+                value,
+            )));
+        }
+
+        self.positions.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        Ok(None)
+    }
+
+    /// Removes `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &Value) -> Result<Option<Value>, String> {
+        let Some(position) = self.positions.remove(&Key::new(key)?) else {
+            return Ok(None);
+        };
+
+        let (_, value) = self.entries.remove(position);
+        for later in self.positions.values_mut() {
+            if *later > position {
+                *later -= 1;
+            }
+        }
+        Ok(Some(value))
+    }
+
+    /// The entries in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (Value, Value)> + '_ {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.to_value(), value.clone()))
+    }
+
+    pub fn keys(&self) -> Vec<Value> {
+        self.entries.iter().map(|(key, _)| key.to_value()).collect()
+    }
+
+    pub fn values(&self) -> Vec<Value> {
+        self.entries
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    /// Empties the map, returning its values.
+    pub(crate) fn take_values(&mut self) -> impl Iterator<Item = Value> {
+        self.positions.clear();
+        std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|(_, value)| value)
+    }
+}
+
+impl Drop for Map {
+    fn drop(&mut self) {
+        types::drop_values(self.take_values());
+    }
+}
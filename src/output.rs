@@ -0,0 +1,100 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Write},
+    rc::Rc,
+};
+
+/// Destination for text an [`Interpreter`](crate::Interpreter) produces:
+/// the output of `print` statements or rendered error reports. Defaults to
+/// the process's stdout and stderr, but can be pointed at any writer or
+/// callback so a host can capture it.
+///
+/// ```
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// use rubrs::{Interpreter, Output};
+///
+/// let lines = Rc::new(RefCell::new(Vec::new()));
+/// let captured = lines.clone();
+/// let interpreter = Interpreter::builder()
+///     .output(Output::callback(move |line| captured.borrow_mut().push(line.to_string())))
+///     .build();
+///
+/// interpreter.parse_and_run("print 1 + 2; print \"done\";").unwrap();
+/// assert_eq!(*lines.borrow(), ["3", "done"]);
+/// ```
+#[derive(Clone)]
+pub struct Output(Sink);
+
+type Callback = dyn FnMut(&str);
+
+#[derive(Clone)]
+enum Sink {
+    Stdout,
+    Stderr,
+    Writer(Rc<RefCell<dyn Write>>),
+    Callback(Rc<RefCell<Callback>>),
+}
+
+impl Output {
+    pub fn stdout() -> Self {
+        Self(Sink::Stdout)
+    }
+
+    pub fn stderr() -> Self {
+        Self(Sink::Stderr)
+    }
+
+    /// Writes each printed value or error report to `writer`, followed by a
+    /// newline. The writer is shared, so the host can keep a handle to read
+    /// back what was written to a buffer.
+    ///
+    /// ```
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// use rubrs::{Interpreter, Output};
+    ///
+    /// let buffer = Rc::new(RefCell::new(Vec::new()));
+    /// let interpreter = Interpreter::builder()
+    ///     .output(Output::writer(buffer.clone()))
+    ///     .build();
+    ///
+    /// interpreter.parse_and_run("print \"hi\";").unwrap();
+    /// assert_eq!(*buffer.borrow(), b"hi\n");
+    /// ```
+    pub fn writer<W: Write + 'static>(writer: Rc<RefCell<W>>) -> Self {
+        Self(Sink::Writer(writer))
+    }
+
+    /// Calls `callback` with each printed value or error report, without a
+    /// trailing newline.
+    pub fn callback(callback: impl FnMut(&str) + 'static) -> Self {
+        Self(Sink::Callback(Rc::new(RefCell::new(callback))))
+    }
+
+    /// Emits one printed value or error report. Write failures are ignored:
+    /// a script has no way to handle them, and they shouldn't abort it either.
+    pub(crate) fn emit(&self, text: &str) {
+        let _ = match &self.0 {
+            Sink::Stdout => writeln!(io::stdout(), "{}", text),
+            Sink::Stderr => writeln!(io::stderr(), "{}", text),
+            Sink::Writer(writer) => writeln!(writer.borrow_mut(), "{}", text),
+            Sink::Callback(callback) => {
+                (callback.borrow_mut())(text);
+                Ok(())
+            }
+        };
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Sink::Stdout => write!(f, "Output::stdout()"),
+            Sink::Stderr => write!(f, "Output::stderr()"),
+            Sink::Writer(_) => write!(f, "Output::writer(..)"),
+            Sink::Callback(_) => write!(f, "Output::callback(..)"),
+        }
+    }
+}
@@ -1,44 +1,133 @@
-use std::mem;
-
-use crate::types::{Expr, Stmt, Token, TokenType, Value};
+use std::{mem, rc::Rc};
+
+use crate::{
+    error::LoxError,
+    stack,
+    types::{Expr, Stmt, Token, TokenType, Value},
+};
+
+/// Deepest nesting of statements and expressions the parser accepts. The
+/// parser and the interpreter check how much stack they have left, but the
+/// resolver and dropping the tree don't, so this bounds them instead: the
+/// resolver takes under 1KB of stack per level in a debug build. Chains of
+/// operators, calls, property accesses and indexing don't count, since
+/// everything walks down those in a loop.
+const MAX_NESTING: usize = 500;
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<LoxError>,
+    trailing_expression: bool,
+    /// Nesting of the code being parsed: statements, groupings, call
+    /// arguments, unary operators and the right side of assignments.
+    depth: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            trailing_expression: false,
+            depth: 0,
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
+    /// Accepts a final expression statement without its semicolon, so that
+    /// snippets like `1 + 2` can be evaluated directly.
+    pub fn allow_trailing_expression(mut self, allow: bool) -> Self {
+        self.trailing_expression = allow;
+        self
+    }
+
+    /// Parses the whole program, recovering at statement boundaries after a
+    /// syntax error so that every error in the source is reported at once.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LoxError>> {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
 
-        Ok(statements)
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(mem::take(&mut self.errors))
+        }
+    }
+
+    fn declaration(&mut self) -> Option<Stmt> {
+        match self.nested(Self::try_declaration) {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                self.errors.push(error);
+                self.synchronize();
+                None
+            }
+        }
     }
 
-    fn declaration(&mut self) -> Result<Stmt, String> {
-        if self.match_token(vec![TokenType::Fun]) {
+    fn try_declaration(&mut self) -> Result<Stmt, LoxError> {
+        if self.match_token(vec![TokenType::Class]) {
+            return self.class_declaration();
+        }
+        // 'fun' not followed by a name starts an anonymous function
+        // expression, which is parsed as part of an expression statement.
+        if self.check(TokenType::Fun) && self.check_next(TokenType::Identifier) {
+            self.advance();
             return self.function("function");
         }
         if self.match_token(vec![TokenType::Var]) {
             return self.var_declaration();
         }
+        if self.match_token(vec![TokenType::Import]) {
+            return self.import_declaration();
+        }
+        // 'from' is only a keyword when a module path follows it, so it can
+        // still be used as a name.
+        if self.check_word("from") && self.check_next(TokenType::String(String::new())) {
+            self.advance();
+            return self.selective_import_declaration();
+        }
 
-        // TODO: add synchronize
         self.statement()
     }
 
-    fn var_declaration(&mut self) -> Result<Stmt, String> {
-        let name = self.consume(
-            TokenType::Identifier(String::new()),
-            "Expect variable name.",
-        )?;
+    fn class_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
+
+        let superclass = if self.match_token(vec![TokenType::Less]) {
+            self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            Some(Expr::Variable {
+                name: self.previous(),
+                slot: None,
+            })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
 
         let initializer = if self.match_token(vec![TokenType::Equal]) {
             Some(self.expression()?)
@@ -54,7 +143,45 @@ impl Parser {
         Ok(Stmt::Var { name, initializer })
     }
 
-    fn statement(&mut self) -> Result<Stmt, String> {
+    fn import_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let path = self.consume(
+            TokenType::String(String::new()),
+            "Expect module path after 'import'.",
+        )?;
+        self.consume_word("as", "Expect 'as' after module path.")?;
+        let name = self.consume(TokenType::Identifier, "Expect module name after 'as'.")?;
+        self.consume(TokenType::Semicolon, "Expect ';' after import.")?;
+
+        Ok(Stmt::Import { path, name })
+    }
+
+    fn selective_import_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let path = self.consume(
+            TokenType::String(String::new()),
+            "Expect module path after 'from'.",
+        )?;
+        self.consume(TokenType::Import, "Expect 'import' after module path.")?;
+
+        let mut names = vec![self.consume(TokenType::Identifier, "Expect name to import.")?];
+        while self.match_token(vec![TokenType::Comma]) {
+            names.push(self.consume(TokenType::Identifier, "Expect name to import.")?);
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after import.")?;
+
+        Ok(Stmt::FromImport { path, names })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, LoxError> {
+        self.nested(Self::parse_statement)
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, LoxError> {
+        if self.match_token(vec![TokenType::Break]) {
+            return self.loop_jump_statement("break");
+        }
+        if self.match_token(vec![TokenType::Continue]) {
+            return self.loop_jump_statement("continue");
+        }
         if self.match_token(vec![TokenType::For]) {
             return self.for_statement();
         }
@@ -67,6 +194,12 @@ impl Parser {
         if self.match_token(vec![TokenType::Return]) {
             return self.return_statement();
         }
+        if self.match_token(vec![TokenType::Throw]) {
+            return self.throw_statement();
+        }
+        if self.match_token(vec![TokenType::Try]) {
+            return self.try_statement();
+        }
         if self.match_token(vec![TokenType::While]) {
             return self.while_statement();
         }
@@ -77,19 +210,37 @@ impl Parser {
         self.expression_statement()
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, String> {
+    fn while_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
         let body = self.statement()?;
 
         Ok(Stmt::While {
+            keyword,
             condition,
             body: Box::new(body),
+            increment: None,
         })
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, String> {
+    /// Parses `break;` or `continue;` after the keyword.
+    fn loop_jump_statement(&mut self, kind: &str) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        self.consume(
+            TokenType::Semicolon,
+            &format!("Expect ';' after '{}'.", kind),
+        )?;
+
+        Ok(match keyword.token_type {
+            TokenType::Break => Stmt::Break { keyword },
+            _ => Stmt::Continue { keyword },
+        })
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if self.match_token(vec![TokenType::Semicolon]) {
@@ -114,27 +265,23 @@ impl Parser {
         };
         self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
-        let mut body = self.statement()?;
-
-        if let Some(increment) = increment {
-            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
-        }
+        let body = self.statement()?;
 
-        body = Stmt::While {
+        let mut body = Stmt::While {
+            keyword,
             condition: condition.unwrap_or(Expr::Literal(Value::Boolean(true))),
             body: Box::new(body),
+            increment,
         };
 
         if let Some(initializer) = initializer {
             body = Stmt::Block(vec![initializer, body]);
         }
 
-        println!("{:#?}", body);
-
         Ok(body)
     }
 
-    fn if_statement(&mut self) -> Result<Stmt, String> {
+    fn if_statement(&mut self) -> Result<Stmt, LoxError> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
@@ -153,11 +300,13 @@ impl Parser {
         })
     }
 
-    fn block(&mut self) -> Result<Vec<Stmt>, String> {
+    fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
         let mut statements = Vec::new();
 
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?);
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
 
         self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
@@ -165,13 +314,13 @@ impl Parser {
         Ok(statements)
     }
 
-    fn print_statement(&mut self) -> Result<Stmt, String> {
+    fn print_statement(&mut self) -> Result<Stmt, LoxError> {
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
         Ok(Stmt::Print(value))
     }
 
-    fn return_statement(&mut self) -> Result<Stmt, String> {
+    fn return_statement(&mut self) -> Result<Stmt, LoxError> {
         let keyword = self.previous();
         let value = if !self.check(TokenType::Semicolon) {
             Some(self.expression()?)
@@ -183,35 +332,89 @@ impl Parser {
         Ok(Stmt::Return { keyword, value })
     }
 
-    fn expression_statement(&mut self) -> Result<Stmt, String> {
+    fn throw_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after thrown value.")?;
+        Ok(Stmt::Throw { keyword, value })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, LoxError> {
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+
+        let catch = if self.match_token(vec![TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self.consume(TokenType::Identifier, "Expect error name.")?;
+            self.consume(TokenType::RightParen, "Expect ')' after error name.")?;
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body.")?;
+            Some((name, self.block()?))
+        } else {
+            None
+        };
+
+        let finally = if self.match_token(vec![TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(self.peek(), "Expect 'catch' or 'finally' after try block."));
+        }
+
+        Ok(Stmt::Try {
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
         let expr = self.expression()?;
+        if self.trailing_expression && self.is_at_end() {
+            return Ok(Stmt::Expression(expr));
+        }
+
         self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
         Ok(Stmt::Expression(expr))
     }
 
-    fn function(&mut self, kind: &str) -> Result<Stmt, String> {
-        let name = self.consume(
-            TokenType::Identifier(String::new()),
-            &format!("Expect {} name.", kind),
-        )?;
+    fn function(&mut self, kind: &str) -> Result<Stmt, LoxError> {
+        let name = self.consume(TokenType::Identifier, &format!("Expect {} name.", kind))?;
 
         self.consume(
             TokenType::LeftParen,
             &format!("Expect '(' after {} name.", kind),
         )?;
+        let parameters = self.parameters()?;
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        let body = self.block()?;
 
+        Ok(Stmt::Function {
+            name,
+            parameters,
+            body: Rc::new(body),
+        })
+    }
+
+    /// Parses a parameter list, after its opening '(' and up to and
+    /// including the closing ')'.
+    fn parameters(&mut self) -> Result<Vec<Token>, LoxError> {
         let mut parameters = Vec::new();
 
         if !self.check(TokenType::RightParen) {
             loop {
                 if parameters.len() >= 255 {
-                    return Err(self.error(self.peek(), "Can't have more than 255 parameters."));
+                    let error = self.error(self.peek(), "Can't have more than 255 parameters.");
+                    self.errors.push(error);
                 }
 
-                parameters.push(self.consume(
-                    TokenType::Identifier(String::new()),
-                    "Expect parameter name.",
-                )?);
+                parameters.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
 
                 if !self.match_token(vec![TokenType::Comma]) {
                     break;
@@ -220,45 +423,88 @@ impl Parser {
         }
 
         self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
-        self.consume(
-            TokenType::LeftBrace,
-            &format!("Expect '{{' before {} body.", kind),
-        )?;
-        let body = self.block()?;
 
-        Ok(Stmt::Function {
-            name,
+        Ok(parameters)
+    }
+
+    /// Parses an anonymous function after its 'fun' keyword. The body is
+    /// either a block or, after '=>', a single expression whose value is
+    /// returned.
+    fn lambda(&mut self) -> Result<Expr, LoxError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+        let parameters = self.parameters()?;
+
+        let body = if self.match_token(vec![TokenType::Arrow]) {
+            let arrow = self.previous();
+            vec![Stmt::Return {
+                keyword: arrow,
+                value: Some(self.expression()?),
+            }]
+        } else {
+            self.consume(TokenType::LeftBrace, "Expect '{' before function body.")?;
+            self.block()?
+        };
+
+        Ok(Expr::Lambda {
             parameters,
-            body,
+            body: Rc::new(body),
         })
     }
 
-    fn expression(&mut self) -> Result<Expr, String> {
-        self.assignment()
+    fn expression(&mut self) -> Result<Expr, LoxError> {
+        self.nested(Self::assignment)
     }
 
-    fn assignment(&mut self) -> Result<Expr, String> {
+    fn assignment(&mut self) -> Result<Expr, LoxError> {
         let expr = self.or()?;
 
         if self.match_token(vec![TokenType::Equal]) {
             let equals = self.previous();
+            self.deepen()?;
             let value = self.assignment()?;
 
-            match expr {
-                Expr::Variable(name) => {
+            // `Expr` drops its chains itself, so the target's operands are
+            // taken out of it rather than moved.
+            let mut expr = expr;
+            match &mut expr {
+                Expr::Variable { name, .. } => {
                     return Ok(Expr::Assign {
-                        name,
+                        name: name.clone(),
+                        value: Box::new(value),
+                        slot: None,
+                    })
+                }
+                Expr::Get { object, name } => {
+                    return Ok(Expr::Set {
+                        object: Box::new(object.take()),
+                        name: name.clone(),
+                        value: Box::new(value),
+                    })
+                }
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => {
+                    return Ok(Expr::SetIndex {
+                        object: Box::new(object.take()),
+                        bracket: bracket.clone(),
+                        index: Box::new(index.take()),
                         value: Box::new(value),
                     })
                 }
-                _ => return Err(self.error(equals, "Invalid assignment target.")),
+                _ => {
+                    let error = self.error(equals, "Invalid assignment target.");
+                    self.errors.push(error);
+                    return Ok(expr);
+                }
             }
         }
 
         Ok(expr)
     }
 
-    fn or(&mut self) -> Result<Expr, String> {
+    fn or(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.and()?;
 
         while self.match_token(vec![TokenType::Or]) {
@@ -274,7 +520,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn and(&mut self) -> Result<Expr, String> {
+    fn and(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.equality()?;
 
         while self.match_token(vec![TokenType::And]) {
@@ -290,7 +536,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
+    fn equality(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.comparison()?;
 
         while self.match_token(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
@@ -306,7 +552,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, String> {
+    fn comparison(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.term()?;
 
         while self.match_token(vec![
@@ -327,7 +573,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, String> {
+    fn term(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.factor()?;
 
         while self.match_token(vec![TokenType::Minus, TokenType::Plus]) {
@@ -343,7 +589,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
+    fn factor(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.unary()?;
 
         while self.match_token(vec![TokenType::Slash, TokenType::Star]) {
@@ -359,9 +605,10 @@ impl Parser {
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
+    fn unary(&mut self) -> Result<Expr, LoxError> {
         if self.match_token(vec![TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous();
+            self.deepen()?;
             let right = self.unary()?;
             return Ok(Expr::Unary {
                 operator,
@@ -372,12 +619,27 @@ impl Parser {
         self.call()
     }
 
-    fn call(&mut self) -> Result<Expr, String> {
+    fn call(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.primary()?;
 
         loop {
             if self.match_token(vec![TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
+            } else if self.match_token(vec![TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
+            } else if self.match_token(vec![TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
             } else {
                 break;
             }
@@ -386,13 +648,14 @@ impl Parser {
         Ok(expr)
     }
 
-    fn finish_call(&mut self, callee: Expr) -> Result<Expr, String> {
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, LoxError> {
         let mut arguments = Vec::new();
 
         if !self.check(TokenType::RightParen) {
             loop {
                 if arguments.len() >= 255 {
-                    return Err(self.error(self.peek(), "Can't have more than 255 arguments."));
+                    let error = self.error(self.peek(), "Can't have more than 255 arguments.");
+                    self.errors.push(error);
                 }
 
                 arguments.push(self.expression()?);
@@ -412,7 +675,7 @@ impl Parser {
         })
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary(&mut self) -> Result<Expr, LoxError> {
         match self.peek().token_type {
             TokenType::False => {
                 self.advance();
@@ -434,9 +697,35 @@ impl Parser {
                 self.advance();
                 Ok(Expr::Literal(Value::String(string)))
             }
-            TokenType::Identifier(_) => {
+            TokenType::Super => {
                 self.advance();
-                Ok(Expr::Variable(self.previous()))
+                let keyword = self.previous();
+                self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+                let method =
+                    self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+                Ok(Expr::Super {
+                    keyword,
+                    method,
+                    slot: None,
+                })
+            }
+            TokenType::This => {
+                self.advance();
+                Ok(Expr::This {
+                    keyword: self.previous(),
+                    slot: None,
+                })
+            }
+            TokenType::Identifier => {
+                self.advance();
+                Ok(Expr::Variable {
+                    name: self.previous(),
+                    slot: None,
+                })
+            }
+            TokenType::Fun => {
+                self.advance();
+                self.lambda()
             }
             TokenType::LeftParen => {
                 self.advance();
@@ -444,6 +733,40 @@ impl Parser {
                 self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
                 Ok(Expr::Grouping(Box::new(expr)))
             }
+            TokenType::LeftBracket => {
+                self.advance();
+                let mut elements = Vec::new();
+                if !self.check(TokenType::RightBracket) {
+                    loop {
+                        elements.push(self.expression()?);
+                        if !self.match_token(vec![TokenType::Comma]) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+                Ok(Expr::List(elements))
+            }
+            // Statements starting with '{' are blocks, so a brace reached
+            // while parsing an expression always opens a map.
+            TokenType::LeftBrace => {
+                self.advance();
+                let brace = self.previous();
+                let mut entries = Vec::new();
+                if !self.check(TokenType::RightBrace) {
+                    loop {
+                        let key = self.expression()?;
+                        self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+                        let value = self.expression()?;
+                        entries.push((key, value));
+                        if !self.match_token(vec![TokenType::Comma]) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+                Ok(Expr::Map { brace, entries })
+            }
             _ => Err(self.error(self.peek(), "Expect expression.")),
         }
     }
@@ -462,7 +785,7 @@ impl Parser {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, String> {
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, LoxError> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
@@ -478,6 +801,27 @@ impl Parser {
         mem::discriminant(&self.peek().token_type) == mem::discriminant(&token_type)
     }
 
+    /// Consumes the identifier `word`, which acts as a keyword in this
+    /// position without being reserved.
+    fn consume_word(&mut self, word: &str, message: &str) -> Result<Token, LoxError> {
+        if self.check_word(word) {
+            return Ok(self.advance());
+        }
+
+        Err(self.error(self.peek(), message))
+    }
+
+    fn check_word(&self, word: &str) -> bool {
+        self.check(TokenType::Identifier) && self.peek().lexeme == word
+    }
+
+    fn check_next(&self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => mem::discriminant(&token.token_type) == mem::discriminant(&token_type),
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -498,15 +842,37 @@ impl Parser {
         self.tokens[self.current - 1].clone()
     }
 
-    fn error(&self, token: Token, message: &str) -> String {
-        if matches!(token.token_type, TokenType::Eof) {
-            format!("{} at end", message)
-        } else {
-            format!("{} at '{}'", message, token.lexeme)
+    fn error(&self, token: Token, message: &str) -> LoxError {
+        LoxError::parse(&token, message)
+    }
+
+    /// Parses a statement or expression nested inside the current one. Errors
+    /// unwind to the nearest enclosing call, so that is where the depth gets
+    /// restored.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        let depth = self.depth;
+        let result = self.deepen().and_then(|()| parse(self));
+        self.depth = depth;
+        result
+    }
+
+    /// Counts one more level of nesting, failing once there are too many.
+    fn deepen(&mut self) -> Result<(), LoxError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING || stack::exhausted() {
+            return Err(self
+                .error(self.peek(), "Too much nesting.")
+                .with_help(format!(
+                    "the parser accepts at most {} levels of nested code",
+                    MAX_NESTING
+                )));
         }
+        Ok(())
     }
 
-    #[allow(unused)]
     fn synchronize(&mut self) {
         self.advance();
 
@@ -519,11 +885,16 @@ impl Parser {
                 TokenType::Class
                 | TokenType::Fun
                 | TokenType::Var
+                | TokenType::Import
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => return,
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try
+                | TokenType::Break
+                | TokenType::Continue => return,
                 _ => self.advance(),
             };
         }
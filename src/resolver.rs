@@ -0,0 +1,437 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    error::LoxError,
+    types::{Expr, Slot, Stmt, Token},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    index: usize,
+    defined: bool,
+}
+
+/// Walks the syntax tree once before it is run, binding every local variable
+/// reference to the environment slot it will live in at runtime and reporting
+/// scoping mistakes that can be caught statically.
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Local>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    /// Number of loops enclosing the current statement within the current
+    /// function, for checking `break` and `continue`.
+    loop_depth: usize,
+    errors: Vec<LoxError>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &mut [Stmt]) -> Result<(), Vec<LoxError>> {
+        self.resolve_statements(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors.clone())
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &mut [Stmt]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &mut Stmt) {
+        match statement {
+            Stmt::Block(statements) => {
+                self.begin_scope();
+                self.resolve_statements(statements);
+                self.end_scope();
+            }
+            Stmt::Break { keyword } => {
+                if self.loop_depth == 0 {
+                    self.error(keyword, "Can't use 'break' outside of a loop.");
+                }
+            }
+            Stmt::Continue { keyword } => {
+                if self.loop_depth == 0 {
+                    self.error(keyword, "Can't use 'continue' outside of a loop.");
+                }
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable {
+                        name: superclass_name,
+                        ..
+                    } = superclass
+                    {
+                        if superclass_name.lexeme == name.lexeme {
+                            self.error(superclass_name, "A class can't inherit from itself.");
+                        }
+                    }
+
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expression(superclass);
+
+                    self.begin_scope();
+                    self.declare_synthetic("super");
+                }
+
+                self.begin_scope();
+                self.declare_synthetic("this");
+
+                for method in methods {
+                    if let Stmt::Function {
+                        name,
+                        parameters,
+                        body,
+                    } = method
+                    {
+                        let declaration = if name.lexeme == "init" {
+                            FunctionType::Initializer
+                        } else {
+                            FunctionType::Method
+                        };
+                        self.resolve_function(parameters, body, declaration);
+                    }
+                }
+
+                self.end_scope();
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.resolve_expression(expr),
+            Stmt::Function {
+                name,
+                parameters,
+                body,
+            } => {
+                self.declare(name);
+                self.define(name);
+                self.resolve_function(parameters, body, FunctionType::Function);
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            Stmt::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.errors.push(
+                            LoxError::resolve(keyword, "Can't return a value from an initializer.")
+                                .with_help("'init' always returns 'this'; use a bare 'return;'"),
+                        );
+                    }
+
+                    self.resolve_expression(value);
+                }
+            }
+            Stmt::Import { name, .. } => {
+                self.declare(name);
+                self.define(name);
+            }
+            Stmt::FromImport { names, .. } => {
+                for name in names {
+                    self.declare(name);
+                    self.define(name);
+                }
+            }
+            Stmt::Throw { value, .. } => self.resolve_expression(value),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.begin_scope();
+                self.resolve_statements(body);
+                self.end_scope();
+
+                if let Some((name, handler)) = catch {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.resolve_statements(handler);
+                    self.end_scope();
+                }
+
+                if let Some(finally) = finally {
+                    self.begin_scope();
+                    self.resolve_statements(finally);
+                    self.end_scope();
+                }
+            }
+            Stmt::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expression(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => {
+                self.resolve_expression(condition);
+
+                self.loop_depth += 1;
+                self.resolve_statement(body);
+                self.loop_depth -= 1;
+
+                if let Some(increment) = increment {
+                    self.resolve_expression(increment);
+                }
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expr: &mut Expr) {
+        // Chains like `a + b + c` can be much longer than real nesting, so
+        // this walks down them in a loop, and resolves the other operands of
+        // every link afterwards, in source order.
+        let mut operands = Vec::new();
+        let mut expr = expr;
+        loop {
+            expr = match expr {
+                Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                    operands.push(&mut **right);
+                    left
+                }
+                Expr::Call {
+                    callee, arguments, ..
+                } => {
+                    operands.extend(arguments.iter_mut().rev());
+                    callee
+                }
+                Expr::Get { object, .. } => object,
+                Expr::Index { object, index, .. } => {
+                    operands.push(&mut **index);
+                    object
+                }
+                _ => break,
+            };
+        }
+
+        self.resolve_operand(expr);
+        while let Some(operand) = operands.pop() {
+            self.resolve_expression(operand);
+        }
+    }
+
+    /// Resolves an expression that isn't a link of a chain.
+    fn resolve_operand(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Assign { name, value, slot } => {
+                self.resolve_expression(value);
+                *slot = self.resolve_local(name);
+            }
+            Expr::Binary { .. }
+            | Expr::Call { .. }
+            | Expr::Get { .. }
+            | Expr::Index { .. }
+            | Expr::Logical { .. } => unreachable!("chains are walked by `resolve_expression`"),
+            Expr::Grouping(expr) => self.resolve_expression(expr),
+            Expr::Lambda {
+                parameters, body, ..
+            } => self.resolve_function(parameters, body, FunctionType::Function),
+            Expr::List(elements) => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expr::Literal(_) => {}
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expression(key);
+                    self.resolve_expression(value);
+                }
+            }
+            Expr::Set { object, value, .. } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+            Expr::Super { keyword, slot, .. } => {
+                match self.current_class {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => {
+                        self.error(keyword, "Can't use 'super' in a class with no superclass.")
+                    }
+                    ClassType::Subclass => {}
+                }
+
+                *slot = self.resolve_local(keyword);
+            }
+            Expr::This { keyword, slot } => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+
+                *slot = self.resolve_local(keyword);
+            }
+            Expr::Unary { right, .. } => self.resolve_expression(right),
+            Expr::Variable { name, slot } => {
+                if let Some(scope) = self.scopes.last() {
+                    if let Some(local) = scope.get(&name.lexeme) {
+                        if !local.defined {
+                            self.error(name, "Can't read local variable in its own initializer.");
+                        }
+                    }
+                }
+
+                *slot = self.resolve_local(name);
+            }
+        }
+    }
+
+    fn resolve_function(
+        &mut self,
+        parameters: &[Token],
+        body: &mut Rc<Vec<Stmt>>,
+        function_type: FunctionType,
+    ) {
+        let body = Rc::get_mut(body).expect("function bodies are shared only once they run");
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+
+        self.begin_scope();
+        for parameter in parameters {
+            self.declare(parameter);
+            self.define(parameter);
+        }
+        self.resolve_statements(body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+    }
+
+    fn resolve_local(&self, name: &Token) -> Option<Slot> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(local) = scope.get(&name.lexeme) {
+                return Some(Slot {
+                    depth,
+                    index: local.index,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.error(name, "Already a variable with this name in this scope.");
+            return;
+        }
+
+        let index = scope.len();
+        scope.insert(
+            name.lexeme.clone(),
+            Local {
+                index,
+                defined: false,
+            },
+        );
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&name.lexeme))
+        {
+            local.defined = true;
+        }
+    }
+
+    fn declare_synthetic(&mut self, name: &str) {
+        let scope = self.scopes.last_mut().expect("synthetic names are local");
+        let index = scope.len();
+        scope.insert(
+            String::from(name),
+            Local {
+                index,
+                defined: true,
+            },
+        );
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(LoxError::resolve(token, message));
+    }
+}
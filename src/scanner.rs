@@ -1,34 +1,71 @@
-use crate::types::{Token, TokenType};
+use std::rc::Rc;
+
+use crate::{
+    error::{LoxError, Span},
+    types::{Token, TokenType},
+};
 
 #[derive(Debug, Clone)]
 pub struct Scanner {
     source: String,
+    file: Option<Rc<str>>,
     tokens: Vec<Token>,
+    errors: Vec<LoxError>,
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,
+    start_line: usize,
+    start_column: usize,
 }
 
 impl Scanner {
     pub(crate) fn new(source: String) -> Self {
         Self {
             source,
+            file: None,
             tokens: Vec::new(),
+            errors: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
         }
     }
 
-    pub(crate) fn scan_tokens(&mut self) -> Vec<Token> {
+    /// Records `file` as the origin of the tokens and errors produced.
+    pub(crate) fn in_file(mut self, file: Option<Rc<str>>) -> Self {
+        self.file = file;
+        self
+    }
+
+    pub(crate) fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<LoxError>> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column(self.current);
             self.scan_token();
         }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, String::from(""), self.line));
-        self.tokens.clone()
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column(self.current);
+        self.tokens.push(Token::new(
+            TokenType::Eof,
+            String::from(""),
+            self.start_line,
+            self.start_column,
+            self.start,
+            self.file.clone(),
+        ));
+
+        if self.errors.is_empty() {
+            Ok(self.tokens.clone())
+        } else {
+            Err(self.errors.clone())
+        }
     }
 
     fn is_at_end(&self) -> bool {
@@ -36,13 +73,46 @@ impl Scanner {
     }
 
     fn advance(&mut self) -> char {
-        self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap()
+        let c = self.source[self.current..].chars().next().unwrap();
+        self.current += c.len_utf8();
+        c
+    }
+
+    /// 1-based column, counted in characters, of the given byte offset on
+    /// the current line.
+    fn column(&self, offset: usize) -> usize {
+        self.source[self.line_start..offset].chars().count() + 1
     }
 
     fn add_token(&mut self, token_type: TokenType) {
         let text = self.source[self.start..self.current].to_string();
-        self.tokens.push(Token::new(token_type, text, self.line));
+        self.tokens.push(Token::new(
+            token_type,
+            text,
+            self.start_line,
+            self.start_column,
+            self.start,
+            self.file.clone(),
+        ));
+    }
+
+    fn error(&mut self, message: &str, help: Option<&str>) {
+        self.errors.push(LoxError::Scan {
+            message: String::from(message),
+            span: Span {
+                line: self.start_line,
+                column: self.start_column,
+                offset: self.start,
+                length: self.current - self.start,
+            },
+            help: help.map(String::from),
+            file: self.file.clone(),
+        });
+    }
+
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
     }
 
     fn match_char(&mut self, expected: char) -> bool {
@@ -50,40 +120,35 @@ impl Scanner {
             return false;
         }
 
-        if self.source.chars().nth(self.current).unwrap() != expected {
+        if self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.current += expected.len_utf8();
         true
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current).unwrap()
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current + 1).unwrap()
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn string(&mut self) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.newline();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            panic!("Unterminated string at line {}", self.line);
+            self.error(
+                "Unterminated string.",
+                Some("add a closing '\"' to end the string"),
+            );
+            return;
         }
 
         self.advance();
@@ -121,22 +186,29 @@ impl Scanner {
         let text = self.source[self.start..self.current].to_string();
         let token_type = match text.as_str() {
             "and" => TokenType::And,
+            "break" => TokenType::Break,
+            "catch" => TokenType::Catch,
             "class" => TokenType::Class,
+            "continue" => TokenType::Continue,
             "else" => TokenType::Else,
             "false" => TokenType::False,
+            "finally" => TokenType::Finally,
             "for" => TokenType::For,
             "fun" => TokenType::Fun,
             "if" => TokenType::If,
+            "import" => TokenType::Import,
             "nil" => TokenType::Nil,
             "or" => TokenType::Or,
             "print" => TokenType::Print,
             "return" => TokenType::Return,
             "super" => TokenType::Super,
             "this" => TokenType::This,
+            "throw" => TokenType::Throw,
             "true" => TokenType::True,
+            "try" => TokenType::Try,
             "var" => TokenType::Var,
             "while" => TokenType::While,
-            _ => TokenType::Identifier(text),
+            _ => TokenType::Identifier,
         };
 
         self.add_token(token_type);
@@ -149,6 +221,9 @@ impl Scanner {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ':' => self.add_token(TokenType::Colon),
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
@@ -166,6 +241,8 @@ impl Scanner {
             '=' => {
                 let token_type = if self.match_char('=') {
                     TokenType::EqualEqual
+                } else if self.match_char('>') {
+                    TokenType::Arrow
                 } else {
                     TokenType::Equal
                 };
@@ -197,7 +274,7 @@ impl Scanner {
                 }
             }
             ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
+            '\n' => self.newline(),
             '"' => self.string(),
             _ => {
                 if c.is_ascii_digit() {
@@ -205,7 +282,7 @@ impl Scanner {
                 } else if c.is_alphabetic() {
                     self.identifier();
                 } else {
-                    panic!("Unexpected character at line {}", self.line);
+                    self.error("Unexpected character.", None);
                 }
             }
         }
@@ -0,0 +1,108 @@
+//! How much stack the current thread has left. The parser, the resolver and
+//! the interpreter all recurse over the script, so they check this to fail
+//! with an error instead of overflowing the stack on deeply nested code.
+
+/// Stack left free below the point where the checks start failing. It has
+/// to hold everything that runs between two checks: in a debug build a Lox
+/// call takes about 13KB, and a native or host function may take more.
+const RESERVE: usize = 256 * 1024;
+
+/// Stack assumed to be usable, counting down from wherever the thread first
+/// checks, on platforms where the real size can't be found out.
+const FALLBACK_SIZE: usize = 1024 * 1024;
+
+/// Whether the current thread is too close to the end of its stack to go on
+/// recursing.
+pub(crate) fn exhausted() -> bool {
+    thread_local! {
+        static LIMIT: usize = match bottom() {
+            Some(bottom) => bottom + RESERVE,
+            None => address().saturating_sub(FALLBACK_SIZE),
+        };
+    }
+
+    address() < LIMIT.with(|limit| *limit)
+}
+
+/// Address of the caller's stack frame. The stack grows downwards on every
+/// supported platform, so this shrinks as calls nest.
+#[inline(always)]
+fn address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Lowest address of the current thread's stack.
+#[cfg(target_os = "linux")]
+fn bottom() -> Option<usize> {
+    use std::ffi::c_void;
+
+    /// `pthread_attr_t` is opaque; this is bigger and more aligned than it is
+    /// on any Linux target.
+    #[repr(C, align(16))]
+    struct Attributes([u8; 128]);
+
+    extern "C" {
+        fn pthread_self() -> usize;
+        fn pthread_getattr_np(thread: usize, attributes: *mut Attributes) -> i32;
+        fn pthread_attr_getstack(
+            attributes: *const Attributes,
+            address: *mut *mut c_void,
+            size: *mut usize,
+        ) -> i32;
+        fn pthread_attr_destroy(attributes: *mut Attributes) -> i32;
+    }
+
+    let mut attributes = Attributes([0; 128]);
+    let mut address = std::ptr::null_mut();
+    let mut size = 0;
+    // SAFETY: `attributes` is large enough for a `pthread_attr_t` and is only
+    // read after `pthread_getattr_np` initialized it.
+    unsafe {
+        if pthread_getattr_np(pthread_self(), &mut attributes) != 0 {
+            return None;
+        }
+        let result = pthread_attr_getstack(&attributes, &mut address, &mut size);
+        pthread_attr_destroy(&mut attributes);
+        (result == 0).then_some(address as usize)
+    }
+}
+
+/// Lowest address of the current thread's stack.
+#[cfg(target_vendor = "apple")]
+fn bottom() -> Option<usize> {
+    use std::ffi::c_void;
+
+    extern "C" {
+        fn pthread_self() -> *mut c_void;
+        fn pthread_get_stackaddr_np(thread: *mut c_void) -> *mut c_void;
+        fn pthread_get_stacksize_np(thread: *mut c_void) -> usize;
+    }
+
+    // SAFETY: both functions only read the attributes of the calling thread.
+    // The address they report is the top of the stack.
+    unsafe {
+        let thread = pthread_self();
+        let top = pthread_get_stackaddr_np(thread) as usize;
+        top.checked_sub(pthread_get_stacksize_np(thread))
+    }
+}
+
+/// Lowest address of the current thread's stack.
+#[cfg(windows)]
+fn bottom() -> Option<usize> {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThreadStackLimits(low: *mut usize, high: *mut usize);
+    }
+
+    let (mut low, mut high) = (0, 0);
+    // SAFETY: the function only writes the two limits.
+    unsafe { GetCurrentThreadStackLimits(&mut low, &mut high) };
+    Some(low)
+}
+
+#[cfg(not(any(target_os = "linux", target_vendor = "apple", windows)))]
+fn bottom() -> Option<usize> {
+    None
+}
@@ -1,6 +1,13 @@
-use std::{cell::RefCell, fmt, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, fmt, mem, rc::Rc};
 
-use crate::{environment::Environment, interpreter::Interpreter};
+use crate::{
+    convert::FromLox,
+    environment::Environment,
+    error::LoxError,
+    interpreter::{Capabilities, Dialect, Interpreter},
+    list::List,
+    map::Map,
+};
 
 #[derive(Debug, Clone)]
 pub enum TokenType {
@@ -8,6 +15,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -20,29 +30,37 @@ pub enum TokenType {
     BangEqual,
     Equal,
     EqualEqual,
+    Arrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
 
-    Identifier(String),
+    Identifier,
     String(String),
     Number(f64),
 
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -53,16 +71,29 @@ pub enum TokenType {
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
-    #[allow(unused)]
     pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+    /// The file the token was scanned from, if any.
+    pub file: Option<Rc<str>>,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: usize) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        line: usize,
+        column: usize,
+        offset: usize,
+        file: Option<Rc<str>>,
+    ) -> Self {
         Self {
             token_type,
             lexeme,
             line,
+            column,
+            offset,
+            file,
         }
     }
 }
@@ -70,101 +101,538 @@ impl Token {
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Block(Vec<Stmt>),
+    Break {
+        keyword: Token,
+    },
+    Class {
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Stmt>,
+    },
+    Continue {
+        keyword: Token,
+    },
     Expression(Expr),
     Function {
         name: Token,
         parameters: Vec<Token>,
-        body: Vec<Stmt>,
+        body: Rc<Vec<Stmt>>,
+    },
+    /// `from "path" import a, b;`
+    FromImport {
+        path: Token,
+        names: Vec<Token>,
     },
     If {
         condition: Expr,
         then_branch: Box<Stmt>,
         else_branch: Option<Box<Stmt>>,
     },
+    /// `import "path" as name;`
+    Import {
+        path: Token,
+        name: Token,
+    },
     Print(Expr),
     Return {
         keyword: Token,
         value: Option<Expr>,
     },
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    /// Has a `catch` clause, a `finally` clause, or both.
+    Try {
+        body: Vec<Stmt>,
+        catch: Option<(Token, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    },
     Var {
         name: Token,
         initializer: Option<Expr>,
     },
+    /// Also used for desugared `for` loops, whose increment runs after
+    /// every iteration of the body, including ones cut short by `continue`.
     While {
+        keyword: Token,
         condition: Expr,
         body: Box<Stmt>,
+        increment: Option<Expr>,
     },
 }
 
+/// Unwinds statement execution: to the enclosing function call when a
+/// `return` is executed, to the enclosing loop on `break` and `continue`, or
+/// all the way out when a runtime error is raised.
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+    Return(Value),
+    Break,
+    Continue,
+    Error(LoxError),
+}
+
+impl From<LoxError> for ControlFlow {
+    fn from(error: LoxError) -> Self {
+        ControlFlow::Error(error)
+    }
+}
+
 impl Stmt {
-    pub fn evaluate(&self, env: &Rc<RefCell<Environment>>) -> Result<(), String> {
+    pub fn evaluate(
+        &self,
+        interpreter: &Interpreter,
+        env: &Rc<RefCell<Environment>>,
+    ) -> Result<(), ControlFlow> {
+        interpreter.count_step();
+
         match self {
-            Stmt::Expression(expr) => {
-                expr.evaluate(env)?;
-            }
+            Stmt::Expression(expr) => execute_expression(interpreter, env, expr),
             Stmt::If {
                 condition,
                 then_branch,
                 else_branch,
+            } => execute_if(interpreter, env, condition, then_branch, else_branch),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => execute_class(interpreter, env, name, superclass, methods),
+            Stmt::Function {
+                name,
+                parameters,
+                body,
+            } => execute_function(env, name, parameters, body),
+            Stmt::Import { path, name } => execute_import(interpreter, env, path, name),
+            Stmt::FromImport { path, names } => execute_from_import(interpreter, env, path, names),
+            Stmt::Print(expr) => execute_print(interpreter, env, expr),
+            Stmt::Break { .. } => Err(ControlFlow::Break),
+            Stmt::Continue { .. } => Err(ControlFlow::Continue),
+            Stmt::Return { value, .. } => execute_return(interpreter, env, value),
+            Stmt::Block(statements) => {
+                execute_block(interpreter, statements, Environment::new_enclosed(env))
+            }
+            Stmt::Throw { keyword, value } => execute_throw(interpreter, env, keyword, value),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => execute_try(interpreter, env, body, catch, finally),
+            Stmt::Var { name, initializer } => execute_var(interpreter, env, name, initializer),
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+                increment,
+            } => execute_while(interpreter, env, keyword, condition, body, increment),
+        }
+    }
+}
+
+// Each statement and expression kind is evaluated by a function of its own,
+// rather than inline in `evaluate`, because a frame of a function is as big as
+// all of its branches together, and `evaluate` is in the middle of every
+// recursion the interpreter does. In a debug build that is the difference
+// between about 21KB and 6KB of stack per level of nesting.
+
+fn execute_expression(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    expr: &Expr,
+) -> Result<(), ControlFlow> {
+    expr.evaluate(interpreter, env)?;
+    Ok(())
+}
+
+fn execute_if(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    condition: &Expr,
+    then_branch: &Stmt,
+    else_branch: &Option<Box<Stmt>>,
+) -> Result<(), ControlFlow> {
+    if interpreter.is_truthy(&condition.evaluate(interpreter, env)?) {
+        then_branch.evaluate(interpreter, env)?;
+    } else if let Some(else_branch) = else_branch {
+        else_branch.evaluate(interpreter, env)?;
+    }
+
+    Ok(())
+}
+
+fn execute_class(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    name: &Token,
+    superclass: &Option<Expr>,
+    methods: &[Stmt],
+) -> Result<(), ControlFlow> {
+    let superclass = match superclass {
+        Some(expr) => match expr.evaluate(interpreter, env)? {
+            Value::Class(class) => Some(class),
+            _ => {
+                return Err(ControlFlow::Error(LoxError::runtime(
+                    name,
+                    format!(
+                        "Superclass '{}' of '{}' must be a class.",
+                        expr, name.lexeme
+                    ),
+                )))
+            }
+        },
+        None => None,
+    };
+
+    let mut closure = env.clone();
+    if let Some(superclass) = &superclass {
+        closure = Environment::new_enclosed(env);
+        closure
+            .borrow_mut()
+            .define(String::from("super"), Value::Class(superclass.clone()));
+    }
+
+    let mut class_methods = HashMap::new();
+    for method in methods {
+        if let Stmt::Function {
+            name,
+            parameters,
+            body,
+        } = method
+        {
+            let function = Function {
+                name: Some(name.lexeme.clone()),
+                parameters: parameters.clone(),
+                body: body.clone(),
+                closure: closure.clone(),
+                is_initializer: name.lexeme == "init",
+            };
+            class_methods.insert(name.lexeme.clone(), function);
+        }
+    }
+
+    let class = Value::Class(Rc::new(Class {
+        name: name.lexeme.clone(),
+        superclass,
+        methods: class_methods,
+    }));
+    env.borrow_mut().define(name.lexeme.clone(), class);
+
+    Ok(())
+}
+
+fn execute_function(
+    env: &Rc<RefCell<Environment>>,
+    name: &Token,
+    parameters: &[Token],
+    body: &Rc<Vec<Stmt>>,
+) -> Result<(), ControlFlow> {
+    let function = Value::Function(Function {
+        name: Some(name.lexeme.clone()),
+        parameters: parameters.to_vec(),
+        body: body.clone(),
+        closure: env.clone(),
+        is_initializer: false,
+    });
+    env.borrow_mut().define(name.lexeme.clone(), function);
+    Ok(())
+}
+
+fn execute_import(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    path: &Token,
+    name: &Token,
+) -> Result<(), ControlFlow> {
+    let module = interpreter.import(path)?;
+    env.borrow_mut()
+        .define(name.lexeme.clone(), Value::Module(module));
+
+    Ok(())
+}
+
+fn execute_from_import(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    path: &Token,
+    names: &[Token],
+) -> Result<(), ControlFlow> {
+    let module = interpreter.import(path)?;
+    for name in names {
+        let value = module.get(name)?;
+        env.borrow_mut().define(name.lexeme.clone(), value);
+    }
+
+    Ok(())
+}
+
+fn execute_print(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    expr: &Expr,
+) -> Result<(), ControlFlow> {
+    let value = expr.evaluate(interpreter, env)?;
+    interpreter.print(&value);
+
+    Ok(())
+}
+
+fn execute_return(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    value: &Option<Expr>,
+) -> Result<(), ControlFlow> {
+    let value = match value {
+        Some(value) => value.evaluate(interpreter, env)?,
+        None => Value::Nil,
+    };
+    Err(ControlFlow::Return(value))
+}
+
+fn execute_throw(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    keyword: &Token,
+    value: &Expr,
+) -> Result<(), ControlFlow> {
+    let value = value.evaluate(interpreter, env)?;
+    let message = match &value {
+        Value::Instance(instance) => match instance.borrow().fields.get("message") {
+            Some(Value::String(message)) => Some(message.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let message = message.unwrap_or_else(|| format!("Uncaught exception: {}.", Repr(&value)));
+    Err(LoxError::throw(keyword, message, value).into())
+}
+
+fn execute_try(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    body: &[Stmt],
+    catch: &Option<(Token, Vec<Stmt>)>,
+    finally: &Option<Vec<Stmt>>,
+) -> Result<(), ControlFlow> {
+    let mut result = execute_block(interpreter, body, Environment::new_enclosed(env));
+
+    if let Some((name, handler)) = catch {
+        if let Err(ControlFlow::Error(error)) = result {
+            result = match interpreter.catch(error) {
+                Ok(value) => {
+                    let environment = Environment::new_enclosed(env);
+                    environment.borrow_mut().define(name.lexeme.clone(), value);
+                    execute_block(interpreter, handler, environment)
+                }
+                Err(error) => Err(ControlFlow::Error(error)),
+            };
+        }
+    }
+
+    if let Some(finally) = finally {
+        // Errors that can't be caught skip the finally block too,
+        // or a jump out of it could swallow them.
+        if let Err(ControlFlow::Error(error)) = &result {
+            if !error.is_catchable() {
+                return result;
+            }
+        }
+        // A jump out of the finally block replaces whatever the
+        // try or catch block did.
+        execute_block(interpreter, finally, Environment::new_enclosed(env))?;
+    }
+
+    result?;
+
+    Ok(())
+}
+
+fn execute_var(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    name: &Token,
+    initializer: &Option<Expr>,
+) -> Result<(), ControlFlow> {
+    let value = match initializer {
+        Some(initializer) => initializer.evaluate(interpreter, env)?,
+        None => Value::Nil,
+    };
+    env.borrow_mut().define(name.lexeme.clone(), value);
+
+    Ok(())
+}
+
+fn execute_while(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    keyword: &Token,
+    condition: &Expr,
+    body: &Stmt,
+    increment: &Option<Expr>,
+) -> Result<(), ControlFlow> {
+    while interpreter.is_truthy(&condition.evaluate(interpreter, env)?) {
+        interpreter.check_limits(keyword)?;
+        match body.evaluate(interpreter, env) {
+            Ok(()) | Err(ControlFlow::Continue) => {}
+            Err(ControlFlow::Break) => break,
+            Err(control_flow) => return Err(control_flow),
+        }
+
+        if let Some(increment) = increment {
+            increment.evaluate(interpreter, env)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `statements` in `environment`, a fresh scope for the block.
+fn execute_block(
+    interpreter: &Interpreter,
+    statements: &[Stmt],
+    environment: Rc<RefCell<Environment>>,
+) -> Result<(), ControlFlow> {
+    for statement in statements {
+        statement.evaluate(interpreter, &environment)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}:{}] {:?} {}",
+            self.line, self.column, self.token_type, self.lexeme
+        )
+    }
+}
+
+/// Writes statements as S-expressions, in the same style as [`Expr`].
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stmt::Block(statements) => {
+                write!(f, "(block")?;
+                for statement in statements {
+                    write!(f, " {}", statement)?;
+                }
+                write!(f, ")")
+            }
+            Stmt::Break { .. } => write!(f, "(break)"),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
             } => {
-                if condition.evaluate(env)?.to_boolean() {
-                    then_branch.evaluate(env)?;
-                } else if let Some(else_branch) = else_branch {
-                    else_branch.evaluate(env)?;
+                write!(f, "(class {}", name.lexeme)?;
+                if let Some(superclass) = superclass {
+                    write!(f, " < {}", superclass)?;
+                }
+                for method in methods {
+                    write!(f, " {}", method)?;
                 }
+                write!(f, ")")
             }
+            Stmt::Continue { .. } => write!(f, "(continue)"),
+            Stmt::Expression(expr) => write!(f, "(; {})", expr),
             Stmt::Function {
                 name,
                 parameters,
                 body,
             } => {
-                let function = Value::Function(Function {
-                    name: name.clone(),
-                    parameters: parameters.clone(),
-                    body: body.clone(),
-                    closure: env.clone(),
-                });
-                env.borrow_mut().define(name.lexeme.clone(), function);
-            }
-            Stmt::Print(expr) => {
-                println!("{}", expr.evaluate(env)?);
-            }
-            Stmt::Return { value, .. } => {
-                let value = match value {
-                    Some(value) => value.evaluate(env)?,
-                    None => Value::Nil,
-                };
-                return Err(format!("{}", value));
-            }
-            Stmt::Block(statements) => {
-                let environment = Environment::new_enclosed(env);
-                for statement in statements {
-                    statement.evaluate(&environment)?;
+                write!(f, "(fun {} ({})", name.lexeme, parameter_list(parameters))?;
+                for statement in body.iter() {
+                    write!(f, " {}", statement)?;
                 }
+                write!(f, ")")
             }
-            Stmt::Var { name, initializer } => {
-                let value = match initializer {
-                    Some(initializer) => initializer.evaluate(env)?,
-                    None => Value::Nil,
-                };
-                env.borrow_mut().define(name.lexeme.clone(), value);
+            Stmt::FromImport { path, names } => {
+                write!(f, "(from {} {})", path.lexeme, parameter_list(names))
             }
-            Stmt::While { condition, body } => {
-                while condition.evaluate(env)?.to_boolean() {
-                    body.evaluate(env)?;
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => match else_branch {
+                Some(else_branch) => {
+                    write!(f, "(if-else {} {} {})", condition, then_branch, else_branch)
+                }
+                None => write!(f, "(if {} {})", condition, then_branch),
+            },
+            Stmt::Import { path, name } => write!(f, "(import {} {})", path.lexeme, name.lexeme),
+            Stmt::Print(expr) => write!(f, "(print {})", expr),
+            Stmt::Return { value, .. } => match value {
+                Some(value) => write!(f, "(return {})", value),
+                None => write!(f, "(return)"),
+            },
+            Stmt::Throw { value, .. } => write!(f, "(throw {})", value),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                write!(f, "(try (block")?;
+                for statement in body {
+                    write!(f, " {}", statement)?;
                 }
+                write!(f, ")")?;
+                if let Some((name, handler)) = catch {
+                    write!(f, " (catch {}", name.lexeme)?;
+                    for statement in handler {
+                        write!(f, " {}", statement)?;
+                    }
+                    write!(f, ")")?;
+                }
+                if let Some(finally) = finally {
+                    write!(f, " (finally")?;
+                    for statement in finally {
+                        write!(f, " {}", statement)?;
+                    }
+                    write!(f, ")")?;
+                }
+                write!(f, ")")
             }
+            Stmt::Var { name, initializer } => match initializer {
+                Some(initializer) => write!(f, "(var {} {})", name.lexeme, initializer),
+                None => write!(f, "(var {})", name.lexeme),
+            },
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => match increment {
+                Some(increment) => write!(f, "(while {} {} {})", condition, body, increment),
+                None => write!(f, "(while {} {})", condition, body),
+            },
         }
-
-        Ok(())
     }
 }
 
+fn parameter_list(parameters: &[Token]) -> String {
+    parameters
+        .iter()
+        .map(|parameter| parameter.lexeme.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Location of a local variable as computed by the resolver: how many
+/// environments to walk up from the current one, and the index of the value
+/// inside that environment.
+#[derive(Debug, Clone, Copy)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
 #[derive(Debug, Clone)]
 pub enum Expr {
     Assign {
         name: Token,
         value: Box<Expr>,
+        slot: Option<Slot>,
     },
     Binary {
         left: Box<Expr>,
@@ -176,179 +644,653 @@ pub enum Expr {
         paren: Token,
         arguments: Vec<Expr>,
     },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
     Grouping(Box<Expr>),
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    Lambda {
+        parameters: Vec<Token>,
+        body: Rc<Vec<Stmt>>,
+    },
+    List(Vec<Expr>),
     Literal(Value),
     Logical {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+        slot: Option<Slot>,
+    },
+    This {
+        keyword: Token,
+        slot: Option<Slot>,
+    },
     Unary {
         operator: Token,
         right: Box<Expr>,
     },
-    Variable(Token),
+    Variable {
+        name: Token,
+        slot: Option<Slot>,
+    },
 }
 
 impl Expr {
-    fn evaluate(&self, env: &Rc<RefCell<Environment>>) -> Result<Value, String> {
+    /// The token errors about the expression as a whole point at, if any.
+    fn token(&self) -> Option<&Token> {
         match self {
-            Expr::Assign { name, value } => {
-                let value = value.evaluate(env)?;
-                env.borrow_mut().assign(name, value.clone())?;
-                Ok(value)
-            }
-            Expr::Binary {
-                left,
-                operator,
-                right,
-            } => {
-                let left = left.evaluate(env)?;
-                let right = right.evaluate(env)?;
-                match operator.token_type {
-                    TokenType::Minus => Ok(Value::Number(left.to_number()? - right.to_number()?)),
-                    TokenType::Plus => Ok(Value::Number(left.to_number()? + right.to_number()?)),
-                    TokenType::Slash => Ok(Value::Number(left.to_number()? / right.to_number()?)),
-                    TokenType::Star => Ok(Value::Number(left.to_number()? * right.to_number()?)),
-                    TokenType::Greater => {
-                        Ok(Value::Boolean(left.to_number()? > right.to_number()?))
-                    }
-                    TokenType::GreaterEqual => {
-                        Ok(Value::Boolean(left.to_number()? >= right.to_number()?))
-                    }
-                    TokenType::Less => Ok(Value::Boolean(left.to_number()? < right.to_number()?)),
-                    TokenType::LessEqual => {
-                        Ok(Value::Boolean(left.to_number()? <= right.to_number()?))
-                    }
-                    TokenType::BangEqual => Ok(Value::Boolean(left != right)),
-                    _ => panic!("Unexpected operator {:?}", operator),
-                }
+            Expr::Assign { name, .. }
+            | Expr::Get { name, .. }
+            | Expr::Set { name, .. }
+            | Expr::Variable { name, .. } => Some(name),
+            Expr::Binary { operator, .. }
+            | Expr::Logical { operator, .. }
+            | Expr::Unary { operator, .. } => Some(operator),
+            Expr::Call { paren, .. } => Some(paren),
+            Expr::Index { bracket, .. } | Expr::SetIndex { bracket, .. } => Some(bracket),
+            Expr::Map { brace, .. } => Some(brace),
+            Expr::Super { keyword, .. } | Expr::This { keyword, .. } => Some(keyword),
+            Expr::Grouping(_) | Expr::Lambda { .. } | Expr::List(_) | Expr::Literal(_) => None,
+        }
+    }
+
+    /// The operand a chain like `a + b + c` or `a.b(c)[d]` continues from:
+    /// the left side of a binary operator, the callee of a call, or the object
+    /// of a property access or index. The parser builds these in loops, so
+    /// they can be much longer than anything nested by recursion.
+    fn chained(&self) -> Option<&Expr> {
+        match self {
+            Expr::Binary { left, .. } | Expr::Logical { left, .. } => Some(left),
+            Expr::Call { callee, .. } => Some(callee),
+            Expr::Get { object, .. } | Expr::Index { object, .. } => Some(object),
+            _ => None,
+        }
+    }
+
+    fn chained_mut(&mut self) -> Option<&mut Expr> {
+        match self {
+            Expr::Binary { left, .. } | Expr::Logical { left, .. } => Some(left),
+            Expr::Call { callee, .. } => Some(callee),
+            Expr::Get { object, .. } | Expr::Index { object, .. } => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Splits the chain starting at this expression into its links, outermost
+    /// first, and the operand the innermost link continues from.
+    fn chain(&self) -> (Vec<&Expr>, &Expr) {
+        let mut links = Vec::new();
+        let mut operand = self;
+        while let Some(chained) = operand.chained() {
+            links.push(operand);
+            operand = chained;
+        }
+        (links, operand)
+    }
+
+    /// Takes the expression out, leaving `nil` in its place.
+    pub(crate) fn take(&mut self) -> Expr {
+        mem::replace(self, Expr::Literal(Value::Nil))
+    }
+
+    pub(crate) fn evaluate(
+        &self,
+        interpreter: &Interpreter,
+        env: &Rc<RefCell<Environment>>,
+    ) -> Result<Value, LoxError> {
+        // Every recursive path through the interpreter evaluates expressions
+        // with a token, so checking those is enough to stop it in time.
+        if let Some(token) = self.token() {
+            interpreter.check_stack(token)?;
+        }
+
+        match self {
+            Expr::Assign { name, value, slot } => {
+                evaluate_assign(interpreter, env, name, value, slot)
             }
-            Expr::Call {
-                callee, arguments, ..
-            } => {
-                let calee = callee.evaluate(env)?;
-                let mut evaluated_arguments = Vec::new();
-                for argument in arguments {
-                    evaluated_arguments.push(argument.evaluate(env)?);
-                }
+            Expr::Binary { .. }
+            | Expr::Call { .. }
+            | Expr::Get { .. }
+            | Expr::Index { .. }
+            | Expr::Logical { .. } => evaluate_chain(interpreter, env, self),
+            Expr::Grouping(expr) => expr.evaluate(interpreter, env),
+            Expr::Lambda {
+                parameters, body, ..
+            } => Ok(Value::Function(Function {
+                name: None,
+                parameters: parameters.clone(),
+                body: body.clone(),
+                closure: env.clone(),
+                is_initializer: false,
+            })),
+            Expr::List(elements) => evaluate_list(interpreter, env, elements),
+            Expr::Literal(literal) => Ok(literal.clone()),
+            Expr::Map { brace, entries } => evaluate_map(interpreter, env, brace, entries),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => evaluate_set(interpreter, env, object, name, value),
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => evaluate_set_index(interpreter, env, object, bracket, index, value),
+            Expr::Super {
+                keyword,
+                method,
+                slot,
+            } => evaluate_super(env, keyword, method, slot),
+            Expr::This { keyword, slot } => lookup_variable(interpreter, env, keyword, slot),
+            Expr::Unary { operator, right } => evaluate_unary(interpreter, env, operator, right),
+            Expr::Variable { name, slot } => lookup_variable(interpreter, env, name, slot),
+        }
+    }
+}
 
-                match calee {
-                    Value::Function(function) => {
-                        if function.parameters.len() != evaluated_arguments.len() {
-                            return Err(format!(
-                                "Expected {} arguments but got {}.",
-                                function.parameters.len(),
-                                evaluated_arguments.len()
-                            ));
-                        }
-
-                        let environment = Environment::new_enclosed(&function.closure);
-                        for (parameter, argument) in
-                            function.parameters.iter().zip(evaluated_arguments.iter())
-                        {
-                            environment
-                                .borrow_mut()
-                                .define(parameter.lexeme.clone(), argument.clone());
-                        }
-
-                        for statement in function.body {
-                            match statement {
-                                Stmt::Return { value, .. } => {
-                                    return if let Some(value) = value {
-                                        Ok(value.evaluate(&environment).unwrap())
-                                    } else {
-                                        Ok(Value::Nil)
-                                    }
-                                }
-                                _ => statement.evaluate(&environment).unwrap(),
-                            }
-                        }
-
-                        Ok(Value::Nil)
-                    }
-                    Value::NativeFunction(function) => {
-                        if function.arity() != evaluated_arguments.len() {
-                            return Err(format!(
-                                "Expected {} arguments but got {}.",
-                                function.arity(),
-                                evaluated_arguments.len()
-                            ));
-                        }
-
-                        function.call(&Interpreter::new(), evaluated_arguments)
-                    }
-                    _ => Err(String::from("Can only call functions and classes.")),
+fn evaluate_assign(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    name: &Token,
+    value: &Expr,
+    slot: &Option<Slot>,
+) -> Result<Value, LoxError> {
+    let value = value.evaluate(interpreter, env)?;
+    match slot {
+        Some(slot) => env
+            .borrow_mut()
+            .assign_at(slot.depth, slot.index, value.clone()),
+        None => env.borrow_mut().assign(name, value.clone())?,
+    }
+    Ok(value)
+}
+
+/// Evaluates a chain from its innermost operand outwards, in a loop rather
+/// than by recursing down the left side of every link.
+fn evaluate_chain(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    expr: &Expr,
+) -> Result<Value, LoxError> {
+    let (links, operand) = expr.chain();
+    let mut value = operand.evaluate(interpreter, env)?;
+    for link in links.into_iter().rev() {
+        value = evaluate_link(interpreter, env, link, value)?;
+    }
+    Ok(value)
+}
+
+/// Applies one link of a chain to the value of the operand it continues from.
+/// The function for each kind of link is kept out of line even in release
+/// builds, so that this frame doesn't grow to all of theirs together.
+fn evaluate_link(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    link: &Expr,
+    value: Value,
+) -> Result<Value, LoxError> {
+    match link {
+        Expr::Binary {
+            operator, right, ..
+        } => evaluate_binary(interpreter, env, value, operator, right),
+        Expr::Call {
+            paren, arguments, ..
+        } => evaluate_call(interpreter, env, value, paren, arguments),
+        Expr::Get { name, .. } => evaluate_get(value, name),
+        Expr::Index { bracket, index, .. } => {
+            evaluate_index(interpreter, env, value, bracket, index)
+        }
+        Expr::Logical {
+            operator, right, ..
+        } => evaluate_logical(interpreter, env, value, operator, right),
+        _ => unreachable!("only chained expressions are links"),
+    }
+}
+
+#[inline(never)]
+fn evaluate_binary(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    left: Value,
+    operator: &Token,
+    right: &Expr,
+) -> Result<Value, LoxError> {
+    let right = right.evaluate(interpreter, env)?;
+    binary_operation(left, operator, right)
+}
+
+// Kept apart from `evaluate_binary`, which recursion passes through, so that
+// its frame stays small.
+fn binary_operation(left: Value, operator: &Token, right: Value) -> Result<Value, LoxError> {
+    match operator.token_type {
+        TokenType::BangEqual => return Ok(Value::Boolean(left != right)),
+        TokenType::EqualEqual => return Ok(Value::Boolean(left == right)),
+        _ => {}
+    }
+
+    if let (Value::String(left), Value::String(right)) = (&left, &right) {
+        match operator.token_type {
+            TokenType::Plus => return Ok(Value::String(format!("{}{}", left, right))),
+            TokenType::Greater => return Ok(Value::Boolean(left > right)),
+            TokenType::GreaterEqual => return Ok(Value::Boolean(left >= right)),
+            TokenType::Less => return Ok(Value::Boolean(left < right)),
+            TokenType::LessEqual => return Ok(Value::Boolean(left <= right)),
+            _ => {}
+        }
+    }
+
+    let (left, right) = match (left, right) {
+        (Value::Number(left), Value::Number(right)) => (left, right),
+        _ => {
+            let message = match operator.token_type {
+                TokenType::Plus => "Operands must be two numbers or two strings.",
+                _ => "Operands must be numbers.",
+            };
+            return Err(LoxError::runtime(operator, message));
+        }
+    };
+
+    match operator.token_type {
+        TokenType::Minus => Ok(Value::Number(left - right)),
+        TokenType::Plus => Ok(Value::Number(left + right)),
+        TokenType::Slash => Ok(Value::Number(left / right)),
+        TokenType::Star => Ok(Value::Number(left * right)),
+        TokenType::Greater => Ok(Value::Boolean(left > right)),
+        TokenType::GreaterEqual => Ok(Value::Boolean(left >= right)),
+        TokenType::Less => Ok(Value::Boolean(left < right)),
+        TokenType::LessEqual => Ok(Value::Boolean(left <= right)),
+        _ => panic!("Unexpected operator {:?}", operator),
+    }
+}
+
+#[inline(never)]
+fn evaluate_call(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    callee: Value,
+    paren: &Token,
+    arguments: &[Expr],
+) -> Result<Value, LoxError> {
+    let mut evaluated_arguments = Vec::new();
+    for argument in arguments {
+        evaluated_arguments.push(argument.evaluate(interpreter, env)?);
+    }
+
+    match callee {
+        Value::Function(function) => call(interpreter, &function, paren, evaluated_arguments),
+        Value::NativeFunction(function) => call(interpreter, &function, paren, evaluated_arguments),
+        Value::Class(class) => call(interpreter, &class, paren, evaluated_arguments),
+        Value::HostFunction(function) => call(interpreter, &function, paren, evaluated_arguments),
+        _ => Err(LoxError::runtime(
+            paren,
+            "Can only call functions and classes.",
+        )),
+    }
+}
+
+#[inline(never)]
+fn evaluate_get(object: Value, name: &Token) -> Result<Value, LoxError> {
+    match object {
+        Value::Instance(instance) => Instance::get(&instance, name),
+        Value::Module(module) => module.get(name),
+        _ => Err(LoxError::runtime(
+            name,
+            "Only instances and modules have properties.",
+        )),
+    }
+}
+
+#[inline(never)]
+fn evaluate_index(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    object: Value,
+    bracket: &Token,
+    index: &Expr,
+) -> Result<Value, LoxError> {
+    let index = index.evaluate(interpreter, env)?;
+    match object {
+        Value::List(list) => {
+            let list = list.borrow();
+            let index = list_index(bracket, &index, list.len())?;
+            Ok(list[index].clone())
+        }
+        Value::Map(map) => map
+            .borrow()
+            .get(&index)
+            .map_err(|message| LoxError::runtime(bracket, message))?
+            .ok_or_else(|| undefined_key(bracket, &index)),
+        _ => Err(LoxError::runtime(
+            bracket,
+            "Only lists and maps can be indexed.",
+        )),
+    }
+}
+
+fn evaluate_list(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    elements: &[Expr],
+) -> Result<Value, LoxError> {
+    let mut values = Vec::with_capacity(elements.len());
+    for element in elements {
+        values.push(element.evaluate(interpreter, env)?);
+    }
+    Ok(Value::List(Rc::new(RefCell::new(values.into()))))
+}
+
+#[inline(never)]
+fn evaluate_logical(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    left: Value,
+    operator: &Token,
+    right: &Expr,
+) -> Result<Value, LoxError> {
+    match operator.token_type {
+        TokenType::And => {
+            if !interpreter.is_truthy(&left) {
+                return Ok(left);
+            }
+        }
+        TokenType::Or => {
+            if interpreter.is_truthy(&left) {
+                return Ok(left);
+            }
+        }
+        _ => panic!("Unexpected operator {:?}", operator),
+    }
+    right.evaluate(interpreter, env)
+}
+
+fn evaluate_map(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    brace: &Token,
+    entries: &[(Expr, Expr)],
+) -> Result<Value, LoxError> {
+    let mut map = Map::new();
+    for (key, value) in entries {
+        let key = key.evaluate(interpreter, env)?;
+        let value = value.evaluate(interpreter, env)?;
+        map.insert(&key, value)
+            .map_err(|message| LoxError::runtime(brace, message))?;
+    }
+    Ok(Value::Map(Rc::new(RefCell::new(map))))
+}
+
+fn evaluate_set(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    object: &Expr,
+    name: &Token,
+    value: &Expr,
+) -> Result<Value, LoxError> {
+    match object.evaluate(interpreter, env)? {
+        Value::Instance(instance) => {
+            let value = value.evaluate(interpreter, env)?;
+            instance
+                .borrow_mut()
+                .fields
+                .insert(name.lexeme.clone(), value.clone());
+            Ok(value)
+        }
+        _ => Err(LoxError::runtime(name, "Only instances have fields.")),
+    }
+}
+
+fn evaluate_set_index(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    object: &Expr,
+    bracket: &Token,
+    index: &Expr,
+    value: &Expr,
+) -> Result<Value, LoxError> {
+    let object = object.evaluate(interpreter, env)?;
+    let index = index.evaluate(interpreter, env)?;
+    let value = value.evaluate(interpreter, env)?;
+    match object {
+        Value::List(list) => {
+            let mut list = list.borrow_mut();
+            let index = list_index(bracket, &index, list.len())?;
+            list[index] = value.clone();
+            Ok(value)
+        }
+        Value::Map(map) => {
+            map.borrow_mut()
+                .insert(&index, value.clone())
+                .map_err(|message| LoxError::runtime(bracket, message))?;
+            Ok(value)
+        }
+        _ => Err(LoxError::runtime(
+            bracket,
+            "Only lists and maps can be indexed.",
+        )),
+    }
+}
+
+fn evaluate_super(
+    env: &Rc<RefCell<Environment>>,
+    keyword: &Token,
+    method: &Token,
+    slot: &Option<Slot>,
+) -> Result<Value, LoxError> {
+    let slot = slot.expect("'super' is always resolved to a local");
+    let superclass = match env.borrow().get_at(slot.depth, slot.index) {
+        Value::Class(class) => class,
+        _ => return Err(LoxError::runtime(keyword, "'super' must refer to a class.")),
+    };
+    let instance = match env.borrow().get_at(slot.depth - 1, 0) {
+        Value::Instance(instance) => instance,
+        _ => {
+            return Err(LoxError::runtime(
+                keyword,
+                "'this' must refer to an instance.",
+            ))
+        }
+    };
+
+    match superclass.find_method(&method.lexeme) {
+        Some(function) => Ok(Value::Function(function.bind(&instance))),
+        None => Err(LoxError::runtime(
+            method,
+            format!(
+                "Undefined method '{}' on superclass '{}'.",
+                method.lexeme, superclass.name
+            ),
+        )),
+    }
+}
+
+fn evaluate_unary(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    operator: &Token,
+    right: &Expr,
+) -> Result<Value, LoxError> {
+    let right = right.evaluate(interpreter, env)?;
+    match operator.token_type {
+        TokenType::Minus => match right {
+            Value::Number(number) => Ok(Value::Number(-number)),
+            _ => Err(LoxError::runtime(operator, "Operand must be a number.")),
+        },
+        TokenType::Bang => Ok(Value::Boolean(!interpreter.is_truthy(&right))),
+        _ => panic!("Unexpected operator {:?}", operator),
+    }
+}
+
+/// Turns a Lox index into a position in a list of `length` elements. Negative
+/// indices count back from the end, so `-1` is the last element.
+fn list_index(bracket: &Token, index: &Value, length: usize) -> Result<usize, LoxError> {
+    let Value::Number(number) = index else {
+        return Err(LoxError::runtime(bracket, "List index must be a number."));
+    };
+    if number.fract() != 0.0 {
+        return Err(LoxError::runtime(bracket, "List index must be an integer."));
+    }
+
+    let position = if *number < 0.0 {
+        *number + length as f64
+    } else {
+        *number
+    };
+    if position < 0.0 || position >= length as f64 {
+        return Err(LoxError::runtime(
+            bracket,
+            format!(
+                "Index {} is out of bounds for a list of length {}.",
+                number, length
+            ),
+        ));
+    }
+
+    Ok(position as usize)
+}
+
+fn undefined_key(token: &Token, key: &Value) -> LoxError {
+    LoxError::runtime(token, format!("Undefined key {}.", Repr(key)))
+}
+
+fn lookup_variable(
+    interpreter: &Interpreter,
+    env: &Rc<RefCell<Environment>>,
+    name: &Token,
+    slot: &Option<Slot>,
+) -> Result<Value, LoxError> {
+    match slot {
+        Some(slot) => Ok(env.borrow().get_at(slot.depth, slot.index)),
+        None => env.borrow().get(name).map_err(|error| {
+            NativeFunction::withheld(name, interpreter.capabilities()).unwrap_or(error)
+        }),
+    }
+}
+
+impl Drop for Expr {
+    // The derived drop would recurse down chains, one level per link.
+    fn drop(&mut self) {
+        let mut operand = self.chained_mut().map(Expr::take);
+        while let Some(mut expr) = operand {
+            operand = expr.chained_mut().map(Expr::take);
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        // Chains are written in a loop too: the start of every link, the
+        // innermost operand, then the rest of every link from the inside out.
+        let (links, operand) = self.chain();
+        for link in &links {
+            match link {
+                Expr::Binary { operator, .. } | Expr::Logical { operator, .. } => {
+                    write!(f, "({} ", operator.lexeme)?
                 }
+                Expr::Call { .. } => write!(f, "(")?,
+                Expr::Get { .. } => write!(f, "(. ")?,
+                Expr::Index { .. } => write!(f, "([] ")?,
+                _ => unreachable!("only chained expressions are links"),
             }
-            Expr::Grouping(expr) => expr.evaluate(env),
-            Expr::Literal(literal) => Ok(literal.clone()),
-            Expr::Logical {
-                left,
-                operator,
-                right,
-            } => {
-                let left = left.evaluate(env)?;
-                match operator.token_type {
-                    TokenType::And => {
-                        if !left.to_boolean() {
-                            return Ok(left);
-                        }
-                    }
-                    TokenType::Or => {
-                        if left.to_boolean() {
-                            return Ok(left);
-                        }
-                    }
-                    _ => panic!("Unexpected operator {:?}", operator),
+        }
+        operand.write_operand(f)?;
+        for link in links.into_iter().rev() {
+            match link {
+                Expr::Binary { right, .. } | Expr::Logical { right, .. } => {
+                    write!(f, " {})", right)?
                 }
-                right.evaluate(env)
-            }
-            Expr::Unary { operator, right } => {
-                let right = right.evaluate(env)?;
-                match operator.token_type {
-                    TokenType::Minus => Ok(Value::Number(-right.to_number()?)),
-                    TokenType::Bang => Ok(Value::Boolean(!right.to_boolean())),
-                    _ => panic!("Unexpected operator {:?}", operator),
+                Expr::Call { arguments, .. } => {
+                    for argument in arguments {
+                        write!(f, " {}", argument)?;
+                    }
+                    write!(f, ")")?
                 }
+                Expr::Get { name, .. } => write!(f, " {})", name.lexeme)?,
+                Expr::Index { index, .. } => write!(f, " {})", index)?,
+                _ => unreachable!("only chained expressions are links"),
             }
-            Expr::Variable(name) => Ok(env.borrow().get(name)?.clone()),
         }
+        Ok(())
     }
 }
 
-impl fmt::Display for Expr {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+impl Expr {
+    /// Writes an expression that isn't a link of a chain.
+    fn write_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expr::Assign { name, value } => write!(f, "({} = {})", name.lexeme, value),
-            Expr::Binary {
-                left,
-                operator,
-                right,
-            } => write!(f, "({} {} {})", operator.lexeme, left, right),
-            Expr::Call {
-                callee, arguments, ..
-            } => {
-                let mut arguments_string = String::new();
-                for argument in arguments {
-                    arguments_string.push_str(&format!("{} ", argument));
+            Expr::Assign { name, value, .. } => write!(f, "({} = {})", name.lexeme, value),
+            Expr::Binary { .. }
+            | Expr::Call { .. }
+            | Expr::Get { .. }
+            | Expr::Index { .. }
+            | Expr::Logical { .. } => unreachable!("chains are written by `fmt`"),
+            Expr::Grouping(expr) => write!(f, "(group {})", expr),
+            Expr::Lambda { parameters, body } => {
+                write!(f, "(fun ({})", parameter_list(parameters))?;
+                for statement in body.iter() {
+                    write!(f, " {}", statement)?;
                 }
-                write!(f, "({} {})", callee, arguments_string)
+                write!(f, ")")
             }
-            Expr::Grouping(expr) => write!(f, "(group {})", expr),
-            Expr::Logical {
-                left,
-                operator,
-                right,
-            } => write!(f, "({} {} {})", operator.lexeme, left, right),
-            Expr::Literal(literal) => write!(f, "{}", literal),
+            Expr::List(elements) => {
+                write!(f, "(list")?;
+                for element in elements {
+                    write!(f, " {}", element)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Literal(literal) => write!(f, "{}", Repr(literal)),
+            Expr::Map { entries, .. } => {
+                write!(f, "(map")?;
+                for (key, value) in entries {
+                    write!(f, " ({} {})", key, value)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => write!(f, "(= (. {} {}) {})", object, name.lexeme, value),
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => write!(f, "(= ([] {} {}) {})", object, index, value),
+            Expr::Super { method, .. } => write!(f, "(. super {})", method.lexeme),
+            Expr::This { .. } => write!(f, "this"),
             Expr::Unary { operator, right } => write!(f, "({} {})", operator.lexeme, right),
-            Expr::Variable(name) => write!(f, "{}", name.lexeme),
+            Expr::Variable { name, .. } => write!(f, "{}", name.lexeme),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 pub enum Value {
     Boolean(bool),
     Nil,
@@ -356,27 +1298,127 @@ pub enum Value {
     String(String),
     Function(Function),
     NativeFunction(NativeFunction),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    HostFunction(Rc<HostFunction>),
+    List(Rc<RefCell<List>>),
+    Map(Rc<RefCell<Map>>),
+    Module(Rc<Module>),
+}
+
+/// Number of arguments a function accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exactly(usize),
+    /// Variadic: any number of arguments from the given minimum up.
+    AtLeast(usize),
+}
+
+impl Arity {
+    fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Exactly(arity) => count == *arity,
+            Arity::AtLeast(minimum) => count >= *minimum,
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(arity: usize) -> Self {
+        Arity::Exactly(arity)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exactly(arity) => write!(f, "{}", arity),
+            Arity::AtLeast(minimum) => write!(f, "at least {}", minimum),
+        }
+    }
 }
 
 trait Callable {
-    fn arity(&self) -> usize;
-    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, String>;
+    fn name(&self) -> String;
+    fn arity(&self) -> Arity;
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, LoxError>;
+}
+
+fn call(
+    interpreter: &Interpreter,
+    callable: &dyn Callable,
+    paren: &Token,
+    arguments: Vec<Value>,
+) -> Result<Value, LoxError> {
+    if !callable.arity().accepts(arguments.len()) {
+        return Err(LoxError::runtime(
+            paren,
+            format!(
+                "Expected {} arguments but got {}.",
+                callable.arity(),
+                arguments.len()
+            ),
+        ));
+    }
+
+    interpreter.check_limits(paren)?;
+    interpreter.push_frame(callable.name(), paren)?;
+    let result = callable
+        .call(interpreter, paren, arguments)
+        .map_err(|error| interpreter.attach_backtrace(error));
+    interpreter.pop_frame();
+
+    result
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
-    name: Token,
+    /// `None` for anonymous functions.
+    name: Option<String>,
     parameters: Vec<Token>,
-    body: Vec<Stmt>,
+    body: Rc<Vec<Stmt>>,
     closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl Function {
+    fn bind(&self, instance: &Rc<RefCell<Instance>>) -> Function {
+        let environment = Environment::new_enclosed(&self.closure);
+        environment
+            .borrow_mut()
+            .define(String::from("this"), Value::Instance(instance.clone()));
+
+        Function {
+            closure: environment,
+            ..self.clone()
+        }
+    }
+
+    fn this(&self) -> Value {
+        self.closure.borrow().get_at(0, 0)
+    }
 }
 
 impl Callable for Function {
-    fn arity(&self) -> usize {
-        self.parameters.len()
+    fn name(&self) -> String {
+        self.name.as_deref().unwrap_or("anonymous").to_string()
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Exactly(self.parameters.len())
     }
 
-    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, String> {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        _paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, LoxError> {
         let environment = Environment::new_enclosed(&self.closure);
         for (parameter, argument) in self.parameters.iter().zip(arguments.iter()) {
             environment
@@ -384,37 +1426,316 @@ impl Callable for Function {
                 .define(parameter.lexeme.clone(), argument.clone());
         }
 
-        interpreter.run(self.body.clone())?;
+        for statement in self.body.iter() {
+            match statement.evaluate(interpreter, &environment) {
+                Ok(()) => {}
+                Err(ControlFlow::Return(value)) => {
+                    if self.is_initializer {
+                        return Ok(self.this());
+                    }
+
+                    return Ok(value);
+                }
+                Err(ControlFlow::Break | ControlFlow::Continue) => {
+                    unreachable!("the resolver rejects 'break' and 'continue' outside loops")
+                }
+                Err(ControlFlow::Error(error)) => return Err(error),
+            }
+        }
+
+        if self.is_initializer {
+            return Ok(self.this());
+        }
 
         Ok(Value::Nil)
     }
 }
 
+/// A function value is only equal to itself: the same declaration closed over
+/// the same environment. Two closures with the same name are distinct.
 impl PartialEq for Function {
     fn eq(&self, other: &Self) -> bool {
-        self.name.lexeme == other.name.lexeme
+        Rc::ptr_eq(&self.body, &other.body) && Rc::ptr_eq(&self.closure, &other.closure)
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    name: String,
+    superclass: Option<Rc<Class>>,
+    methods: HashMap<String, Function>,
+}
+
+impl Class {
+    /// A class without methods, for values the interpreter creates itself.
+    pub(crate) fn builtin(name: &str) -> Rc<Class> {
+        Rc::new(Class {
+            name: name.to_string(),
+            superclass: None,
+            methods: HashMap::new(),
+        })
+    }
+
+    fn find_method(&self, name: &str) -> Option<&Function> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(method);
+        }
+
+        match &self.superclass {
+            Some(superclass) => superclass.find_method(name),
+            None => None,
+        }
+    }
+}
+
+impl Callable for Rc<Class> {
+    fn name(&self) -> String {
+        self.name.clone()
     }
+
+    fn arity(&self) -> Arity {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => Arity::Exactly(0),
+        }
+    }
+
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        let instance = Rc::new(RefCell::new(Instance {
+            class: self.clone(),
+            fields: HashMap::new(),
+        }));
+
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(&instance)
+                .call(interpreter, paren, arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    class: Rc<Class>,
+    fields: HashMap<String, Value>,
 }
 
-impl PartialOrd for Function {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.name.lexeme.partial_cmp(&other.name.lexeme)
+impl Drop for Instance {
+    fn drop(&mut self) {
+        drop_values(mem::take(&mut self.fields).into_values());
     }
 }
 
+impl Instance {
+    pub(crate) fn with_fields<const N: usize>(
+        class: &Rc<Class>,
+        fields: [(&str, Value); N],
+    ) -> Value {
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        Value::Instance(Rc::new(RefCell::new(Instance {
+            class: class.clone(),
+            fields,
+        })))
+    }
+
+    fn get(instance: &Rc<RefCell<Instance>>, name: &Token) -> Result<Value, LoxError> {
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let class = instance.borrow().class.clone();
+        if let Some(method) = class.find_method(&name.lexeme) {
+            return Ok(Value::Function(method.bind(instance)));
+        }
+
+        Err(LoxError::runtime(
+            name,
+            format!("Undefined property '{}'.", name.lexeme),
+        ))
+    }
+}
+
+/// A file loaded with `import`. Its globals are reachable as properties of the
+/// name it was imported as; names it only sees as builtins are not.
+#[derive(Debug)]
+pub struct Module {
+    path: Rc<str>,
+    globals: Rc<RefCell<Environment>>,
+}
+
+impl Module {
+    pub(crate) fn new(path: Rc<str>, globals: Rc<RefCell<Environment>>) -> Self {
+        Self { path, globals }
+    }
+
+    /// The path the module was loaded from, relative to the main script.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn get(&self, name: &Token) -> Result<Value, LoxError> {
+        self.globals
+            .borrow()
+            .own_value(&name.lexeme)
+            .ok_or_else(|| {
+                LoxError::runtime(
+                    name,
+                    format!("Module '{}' has no '{}'.", self.path, name.lexeme),
+                )
+            })
+    }
+}
+
+/// Functions built into the interpreter. Those that reach outside the script
+/// need a capability, which the host can withhold through [`Capabilities`].
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum NativeFunction {
     Clock,
+    ReadFile,
+    WriteFile,
+    Getenv,
+    Exit,
+    ReadLine,
+    Len,
+    Push,
+    Pop,
+    Insert,
+    Remove,
+    Keys,
+    Values,
+    Has,
+    Error,
+}
+
+impl NativeFunction {
+    pub(crate) const ALL: [NativeFunction; 15] = [
+        NativeFunction::Clock,
+        NativeFunction::ReadFile,
+        NativeFunction::WriteFile,
+        NativeFunction::Getenv,
+        NativeFunction::Exit,
+        NativeFunction::ReadLine,
+        NativeFunction::Len,
+        NativeFunction::Push,
+        NativeFunction::Pop,
+        NativeFunction::Insert,
+        NativeFunction::Remove,
+        NativeFunction::Keys,
+        NativeFunction::Values,
+        NativeFunction::Has,
+        NativeFunction::Error,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            NativeFunction::Clock => "clock",
+            NativeFunction::ReadFile => "readFile",
+            NativeFunction::WriteFile => "writeFile",
+            NativeFunction::Getenv => "getenv",
+            NativeFunction::Exit => "exit",
+            NativeFunction::ReadLine => "readLine",
+            NativeFunction::Len => "len",
+            NativeFunction::Push => "push",
+            NativeFunction::Pop => "pop",
+            NativeFunction::Insert => "insert",
+            NativeFunction::Remove => "remove",
+            NativeFunction::Keys => "keys",
+            NativeFunction::Values => "values",
+            NativeFunction::Has => "has",
+            NativeFunction::Error => "Error",
+        }
+    }
+
+    /// Whether scripts running with `capabilities` may call the function.
+    pub(crate) fn is_allowed(&self, capabilities: &Capabilities) -> bool {
+        !matches!(self.capability(capabilities), Some((_, false)))
+    }
+
+    /// The error for a script using `name` when it's undefined because it
+    /// names a function that needs a capability that isn't granted.
+    pub(crate) fn withheld(name: &Token, capabilities: &Capabilities) -> Option<LoxError> {
+        let native = Self::ALL
+            .into_iter()
+            .find(|native| native.name() == name.lexeme)?;
+        let Some((capability, false)) = native.capability(capabilities) else {
+            return None;
+        };
+
+        Some(LoxError::runtime(
+            name,
+            format!(
+                "Can't use '{}': {} access is disabled.",
+                name.lexeme, capability
+            ),
+        ))
+    }
+
+    /// The capability needed to call the function, if any, and whether it's
+    /// granted.
+    fn capability(&self, capabilities: &Capabilities) -> Option<(&'static str, bool)> {
+        match self {
+            NativeFunction::Clock => Some(("clock", capabilities.clock)),
+            NativeFunction::ReadFile | NativeFunction::WriteFile => {
+                Some(("filesystem", capabilities.filesystem))
+            }
+            NativeFunction::Getenv => Some(("environment", capabilities.environment)),
+            NativeFunction::Exit => Some(("process", capabilities.process)),
+            NativeFunction::ReadLine => Some(("stdin", capabilities.stdin)),
+            NativeFunction::Len
+            | NativeFunction::Push
+            | NativeFunction::Pop
+            | NativeFunction::Insert
+            | NativeFunction::Remove
+            | NativeFunction::Keys
+            | NativeFunction::Values
+            | NativeFunction::Has
+            | NativeFunction::Error => None,
+        }
+    }
 }
 
 impl Callable for NativeFunction {
-    fn arity(&self) -> usize {
+    fn name(&self) -> String {
+        NativeFunction::name(self).to_string()
+    }
+
+    fn arity(&self) -> Arity {
         match self {
-            NativeFunction::Clock => 0,
+            NativeFunction::Clock | NativeFunction::ReadLine => Arity::Exactly(0),
+            NativeFunction::ReadFile
+            | NativeFunction::Getenv
+            | NativeFunction::Exit
+            | NativeFunction::Len
+            | NativeFunction::Pop
+            | NativeFunction::Keys
+            | NativeFunction::Values
+            | NativeFunction::Error => Arity::Exactly(1),
+            NativeFunction::WriteFile
+            | NativeFunction::Push
+            | NativeFunction::Remove
+            | NativeFunction::Has => Arity::Exactly(2),
+            NativeFunction::Insert => Arity::Exactly(3),
         }
     }
 
-    fn call(&self, _interpreter: &Interpreter, _arguments: Vec<Value>) -> Result<Value, String> {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        let mut arguments = arguments.into_iter();
+
         match self {
             NativeFunction::Clock => Ok(Value::Number(
                 std::time::SystemTime::now()
@@ -422,32 +1743,264 @@ impl Callable for NativeFunction {
                     .unwrap()
                     .as_secs_f64(),
             )),
+            NativeFunction::ReadFile => {
+                let path: String = argument(paren, 1, arguments.next())?;
+                std::fs::read_to_string(&path)
+                    .map(Value::String)
+                    .map_err(|error| {
+                        LoxError::runtime(
+                            paren,
+                            format!("Could not read file '{}': {}.", path, error),
+                        )
+                    })
+            }
+            NativeFunction::WriteFile => {
+                let path: String = argument(paren, 1, arguments.next())?;
+                let contents: String = argument(paren, 2, arguments.next())?;
+                std::fs::write(&path, contents)
+                    .map(|_| Value::Nil)
+                    .map_err(|error| {
+                        LoxError::runtime(
+                            paren,
+                            format!("Could not write file '{}': {}.", path, error),
+                        )
+                    })
+            }
+            NativeFunction::Getenv => {
+                let name: String = argument(paren, 1, arguments.next())?;
+                Ok(std::env::var(name).map(Value::String).unwrap_or(Value::Nil))
+            }
+            NativeFunction::Exit => {
+                Err(LoxError::exit(paren, argument(paren, 1, arguments.next())?))
+            }
+            NativeFunction::ReadLine => {
+                let mut line = String::new();
+                match std::io::stdin().read_line(&mut line) {
+                    Ok(0) => Ok(Value::Nil),
+                    Ok(_) => {
+                        let length = line.trim_end_matches(['\n', '\r']).len();
+                        line.truncate(length);
+                        Ok(Value::String(line))
+                    }
+                    Err(error) => Err(LoxError::runtime(
+                        paren,
+                        format!("Could not read from stdin: {}.", error),
+                    )),
+                }
+            }
+            NativeFunction::Len => match argument(paren, 1, arguments.next())? {
+                Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+                Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+                Value::String(string) => Ok(Value::Number(string.chars().count() as f64)),
+                value => Err(LoxError::runtime(
+                    paren,
+                    format!(
+                        "Argument 1: expected list, map or string but got {}.",
+                        value.type_name()
+                    ),
+                )),
+            },
+            NativeFunction::Push => {
+                let list = list_argument(paren, arguments.next())?;
+                let value = arguments.next().expect("arity is checked before the call");
+                list.borrow_mut().push(value);
+                Ok(Value::Nil)
+            }
+            NativeFunction::Pop => list_argument(paren, arguments.next())?
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| LoxError::runtime(paren, "Can't pop from an empty list.")),
+            NativeFunction::Insert => {
+                let list = list_argument(paren, arguments.next())?;
+                let index = arguments.next().expect("arity is checked before the call");
+                let value = arguments.next().expect("arity is checked before the call");
+                let mut list = list.borrow_mut();
+                // Inserting at the length appends, so the end is a valid position.
+                let index = list_index(paren, &index, list.len() + 1)?;
+                list.insert(index, value);
+                Ok(Value::Nil)
+            }
+            NativeFunction::Remove => {
+                let collection = arguments.next().expect("arity is checked before the call");
+                let index = arguments.next().expect("arity is checked before the call");
+                match collection {
+                    Value::List(list) => {
+                        let mut list = list.borrow_mut();
+                        let index = list_index(paren, &index, list.len())?;
+                        Ok(list.remove(index))
+                    }
+                    Value::Map(map) => map
+                        .borrow_mut()
+                        .remove(&index)
+                        .map_err(|message| LoxError::runtime(paren, message))?
+                        .ok_or_else(|| undefined_key(paren, &index)),
+                    value => Err(LoxError::runtime(
+                        paren,
+                        format!(
+                            "Argument 1: expected list or map but got {}.",
+                            value.type_name()
+                        ),
+                    )),
+                }
+            }
+            NativeFunction::Keys => {
+                let keys = map_argument(paren, arguments.next())?.borrow().keys();
+                Ok(Value::List(Rc::new(RefCell::new(keys.into()))))
+            }
+            NativeFunction::Values => {
+                let values = map_argument(paren, arguments.next())?.borrow().values();
+                Ok(Value::List(Rc::new(RefCell::new(values.into()))))
+            }
+            NativeFunction::Has => {
+                let map = map_argument(paren, arguments.next())?;
+                let key = arguments.next().expect("arity is checked before the call");
+                let has = map
+                    .borrow()
+                    .contains_key(&key)
+                    .map_err(|message| LoxError::runtime(paren, message))?;
+                Ok(Value::Boolean(has))
+            }
+            NativeFunction::Error => {
+                let message: String = argument(paren, 1, arguments.next())?;
+                Ok(interpreter.error_value(&message, paren.line))
+            }
+        }
+    }
+}
+
+/// Converts an argument of a native function, reporting a mismatch against
+/// its 1-based `position`.
+fn argument<T: FromLox>(
+    paren: &Token,
+    position: usize,
+    value: Option<Value>,
+) -> Result<T, LoxError> {
+    let value = value.expect("arity is checked before the call");
+    T::from_lox(value)
+        .map_err(|message| LoxError::runtime(paren, format!("Argument {}: {}.", position, message)))
+}
+
+/// Takes the list a list function operates on, which is always its first
+/// argument.
+fn list_argument(paren: &Token, value: Option<Value>) -> Result<Rc<RefCell<List>>, LoxError> {
+    match argument(paren, 1, value)? {
+        Value::List(list) => Ok(list),
+        value => Err(LoxError::runtime(
+            paren,
+            format!("Argument 1: expected list but got {}.", value.type_name()),
+        )),
+    }
+}
+
+/// Takes the map a map function operates on, which is always its first
+/// argument.
+fn map_argument(paren: &Token, value: Option<Value>) -> Result<Rc<RefCell<Map>>, LoxError> {
+    match argument(paren, 1, value)? {
+        Value::Map(map) => Ok(map),
+        value => Err(LoxError::runtime(
+            paren,
+            format!("Argument 1: expected map but got {}.", value.type_name()),
+        )),
+    }
+}
+
+type HostFn = dyn Fn(&[Value]) -> Result<Value, String>;
+
+/// A function implemented by the embedding application as a Rust closure and
+/// registered with [`Interpreter::register_fn`].
+pub struct HostFunction {
+    name: String,
+    arity: Arity,
+    function: Box<HostFn>,
+}
+
+impl HostFunction {
+    pub(crate) fn new(name: String, arity: Arity, function: Box<HostFn>) -> Self {
+        Self {
+            name,
+            arity,
+            function,
         }
     }
 }
 
+impl Callable for Rc<HostFunction> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        _interpreter: &Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        (self.function)(&arguments).map_err(|message| LoxError::runtime(paren, message))
+    }
+}
+
+impl fmt::Debug for HostFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Value {
-    fn to_boolean(&self) -> bool {
-        match self {
-            Value::Boolean(boolean) => *boolean,
-            Value::Nil => false,
-            Value::Number(number) => *number != 0.0,
-            Value::String(string) => !string.is_empty(),
-            Value::Function(_) => true,
-            Value::NativeFunction(_) => true,
+    /// Whether the value counts as true in a condition. In standard Lox only
+    /// `nil` and `false` are falsey; the permissive dialect also treats `0` and
+    /// the empty string as falsey.
+    pub fn is_truthy(&self, dialect: Dialect) -> bool {
+        match (self, dialect) {
+            (Value::Nil, _) | (Value::Boolean(false), _) => false,
+            (Value::Number(number), Dialect::Permissive) => *number != 0.0,
+            (Value::String(string), Dialect::Permissive) => !string.is_empty(),
+            _ => true,
         }
     }
 
-    fn to_number(&self) -> Result<f64, String> {
+    /// Name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
         match self {
-            Value::Boolean(boolean) => Ok(*boolean as i32 as f64),
-            Value::Nil => Ok(0.0),
-            Value::Number(number) => Ok(*number),
-            Value::String(string) => Ok(string.parse::<f64>().unwrap()),
-            Value::Function(_) => Err(String::from("Cannot convert function to number.")),
-            Value::NativeFunction(_) => {
-                Err(String::from("Cannot convert native function to number."))
-            }
+            Value::Boolean(_) => "boolean",
+            Value::Nil => "nil",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Function(_) | Value::NativeFunction(_) | Value::HostFunction(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Module(_) => "module",
+        }
+    }
+}
+
+/// Lox equality: values of different types are never equal, numbers follow
+/// IEEE 754 (so `NaN != NaN`), and functions, classes, instances, lists,
+/// maps and modules compare by identity.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Boolean(left), Value::Boolean(right)) => left == right,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::String(left), Value::String(right)) => left == right,
+            (Value::Function(left), Value::Function(right)) => left == right,
+            (Value::NativeFunction(left), Value::NativeFunction(right)) => left == right,
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => Rc::ptr_eq(left, right),
+            (Value::HostFunction(left), Value::HostFunction(right)) => Rc::ptr_eq(left, right),
+            (Value::List(left), Value::List(right)) => Rc::ptr_eq(left, right),
+            (Value::Map(left), Value::Map(right)) => Rc::ptr_eq(left, right),
+            (Value::Module(left), Value::Module(right)) => Rc::ptr_eq(left, right),
+            _ => false,
         }
     }
 }
@@ -459,8 +2012,105 @@ impl fmt::Display for Value {
             Value::Nil => write!(f, "nil"),
             Value::Number(number) => write!(f, "{}", number),
             Value::String(string) => write!(f, "{}", string),
-            Value::Function(function) => write!(f, "<fn {}>", function.name.lexeme),
-            Value::NativeFunction(function) => write!(f, "<native fn {:?}>", function),
+            Value::Function(function) => write!(
+                f,
+                "<fn {}>",
+                function.name.as_deref().unwrap_or("anonymous")
+            ),
+            Value::NativeFunction(function) => write!(f, "<native fn {}>", function.name()),
+            Value::Class(class) => write!(f, "{}", class.name),
+            Value::Instance(instance) => write!(f, "{} instance", instance.borrow().class.name),
+            Value::HostFunction(function) => write!(f, "<native fn {}>", function.name),
+            Value::List(_) | Value::Map(_) => self.write_element(f, &mut Vec::new()),
+            Value::Module(module) => write!(f, "<module {}>", module.path),
         }
     }
 }
+
+/// Deepest nesting of lists and maps written out in full.
+const MAX_WRITE_DEPTH: usize = 100;
+
+impl Value {
+    /// Writes a value the way it appears inside a list or map: strings are
+    /// quoted, and a collection that contains itself, or is nested more than
+    /// `MAX_WRITE_DEPTH` deep, is shown as `[...]` or `{...}` rather than
+    /// being written forever or running out of stack.
+    fn write_element(&self, f: &mut fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::String(string) => write!(f, "{:?}", string),
+            Value::List(list) => {
+                let pointer = Rc::as_ptr(list) as *const ();
+                if seen.contains(&pointer) || seen.len() >= MAX_WRITE_DEPTH {
+                    return write!(f, "[...]");
+                }
+
+                seen.push(pointer);
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.write_element(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let pointer = Rc::as_ptr(map) as *const ();
+                if seen.contains(&pointer) || seen.len() >= MAX_WRITE_DEPTH {
+                    return write!(f, "{{...}}");
+                }
+
+                seen.push(pointer);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.write_element(f, seen)?;
+                    write!(f, ": ")?;
+                    value.write_element(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "}}")
+            }
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+/// Drops values, taking apart in a loop the lists, maps and instances nothing
+/// else refers to. Dropping those the usual way recurses once per level
+/// of nesting, and a script can nest them deeply enough to run out of stack.
+pub(crate) fn drop_values(values: impl IntoIterator<Item = Value>) {
+    let mut pending: Vec<Value> = values.into_iter().collect();
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::List(list) => {
+                if let Some(list) = Rc::into_inner(list) {
+                    pending.append(&mut list.into_inner());
+                }
+            }
+            Value::Map(map) => {
+                if let Some(map) = Rc::into_inner(map) {
+                    pending.extend(map.into_inner().take_values());
+                }
+            }
+            Value::Instance(instance) => {
+                if let Some(instance) = Rc::into_inner(instance) {
+                    pending.extend(mem::take(&mut instance.into_inner().fields).into_values());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Displays a value as it appears inside a collection, with strings quoted.
+struct Repr<'a>(&'a Value);
+
+impl fmt::Display for Repr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_element(f, &mut Vec::new())
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn numbers_follow_the_usual_rules() {
+    let printed = run("
+        print 1 + 2 * 3;
+        print (1 + 2) * 3;
+        print 7 / 2;
+        print 1 - -1;
+        print 1 / 0;
+        print 2 > 1;
+        print 2 <= 1;
+    ");
+    assert_eq!(printed, ["7", "9", "3.5", "2", "inf", "true", "false"]);
+}
+
+#[test]
+fn nothing_is_coerced_to_a_number() {
+    for code in [
+        "print nil + 3;",
+        "print true + 1;",
+        "print \"3\" + 3;",
+        "print nil < 1;",
+        "print true * 2;",
+        "print \"abc\" * 2;",
+        "print 1 - \"1\";",
+        "print 4 / nil;",
+        "print \"a\" >= \"b\" > 1;",
+    ] {
+        let (_, error) = run_err(code);
+        let expected = if code.contains('+') {
+            "Operands must be two numbers or two strings."
+        } else {
+            "Operands must be numbers."
+        };
+        assert_eq!(error.message(), expected, "{}", code);
+    }
+}
+
+#[test]
+fn negation_needs_a_number() {
+    for code in ["print -nil;", "print -\"1\";", "print -true;"] {
+        let (_, error) = run_err(code);
+        assert_eq!(error.message(), "Operand must be a number.", "{}", code);
+    }
+}
+
+#[test]
+fn type_errors_point_at_the_operator() {
+    let (printed, error) = run_err(
+        "print \"ok\";
+         var a = 1;
+         var b = a *
+             nil;",
+    );
+
+    assert_eq!(printed, ["ok"]);
+    assert_eq!(error.span().line, 3);
+    assert_eq!(error.span().column, 20);
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::run_err;
+use rubrs::Frame;
+
+fn frame(function: Option<&str>, line: usize) -> Frame {
+    Frame {
+        function: function.map(String::from),
+        line,
+    }
+}
+
+#[test]
+fn runtime_errors_record_the_calls_they_happened_in() {
+    let (_, error) = run_err(
+        "
+        fun inner() {
+            return 1 + nil;
+        }
+        fun outer() {
+            return inner();
+        }
+        outer();
+        ",
+    );
+
+    assert_eq!(
+        error.backtrace(),
+        [
+            frame(Some("inner"), 3),
+            frame(Some("outer"), 6),
+            frame(None, 8),
+        ]
+    );
+}
+
+#[test]
+fn errors_in_top_level_code_have_no_backtrace() {
+    let (_, error) = run_err("print 1 + nil;");
+    assert!(error.backtrace().is_empty());
+}
+
+#[test]
+fn recursive_calls_are_shown_once() {
+    let (_, error) = run_err(
+        "
+        fun count(n) {
+            if (n == 0) return 1 + nil;
+            return count(n - 1);
+        }
+        count(100);
+        ",
+    );
+
+    assert_eq!(error.backtrace().len(), 102);
+    assert_eq!(
+        error.to_string(),
+        "[line 3:34] Runtime error: Operands must be two numbers or two strings.\n\
+         [line 3] in count()\n\
+         [line 4] in count()\n\
+         ... 99 more calls to count()\n\
+         [line 6] in script"
+    );
+}
+
+#[test]
+fn the_middle_of_a_long_backtrace_is_left_out() {
+    let (_, error) = run_err(
+        "
+        fun ping(n) {
+            if (n == 0) return 1 + nil;
+            return pong(n - 1);
+        }
+        fun pong(n) {
+            return ping(n);
+        }
+        ping(100);
+        ",
+    );
+
+    let shown = error.to_string();
+    let lines: Vec<&str> = shown.lines().collect();
+    assert_eq!(lines.len(), 1 + 10 + 1 + 10);
+    assert_eq!(lines[1], "[line 3] in ping()");
+    assert_eq!(lines[11], "... 182 more calls");
+    assert_eq!(lines[21], "[line 9] in script");
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{interpreter, run, run_err};
+use rubrs::Capabilities;
+
+#[test]
+fn withheld_natives_report_the_capability_they_need() {
+    for (code, message) in [
+        ("clock();", "Can't use 'clock': clock access is disabled."),
+        (
+            "readFile(\"a.txt\");",
+            "Can't use 'readFile': filesystem access is disabled.",
+        ),
+        (
+            "var get = getenv;",
+            "Can't use 'getenv': environment access is disabled.",
+        ),
+        ("exit(0);", "Can't use 'exit': process access is disabled."),
+        (
+            "readLine();",
+            "Can't use 'readLine': stdin access is disabled.",
+        ),
+    ] {
+        let (_, error) = run_err(code);
+        assert_eq!(error.message(), message);
+    }
+}
+
+#[test]
+fn granted_natives_can_be_called() {
+    let capabilities = Capabilities {
+        clock: true,
+        ..Capabilities::none()
+    };
+    let (interpreter, printed) = interpreter(capabilities);
+    interpreter.parse_and_run("print clock() > 0;").unwrap();
+    assert_eq!(printed.take(), ["true"]);
+
+    let errors = interpreter.parse_and_run("getenv(\"HOME\");").unwrap_err();
+    assert_eq!(
+        errors[0].message(),
+        "Can't use 'getenv': environment access is disabled."
+    );
+}
+
+#[test]
+fn scripts_can_define_the_names_of_withheld_natives() {
+    let printed = run("fun clock() { return 42; } print clock();");
+    assert_eq!(printed, ["42"]);
+}
+
+#[test]
+fn other_undefined_names_are_still_undefined() {
+    let (_, error) = run_err("print missing;");
+    assert_eq!(error.message(), "Undefined variable 'missing'.");
+}
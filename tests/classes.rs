@@ -0,0 +1,147 @@
+mod common;
+
+use common::{run, run_err};
+use rubrs::LoxError;
+
+#[test]
+fn classes_and_instances_print_their_names() {
+    let printed = run("
+        class Point {}
+        print Point;
+        print Point();
+    ");
+    assert_eq!(printed, ["Point", "Point instance"]);
+}
+
+#[test]
+fn fields_can_be_set_and_read() {
+    let printed = run("
+        class Box {}
+        var box = Box();
+        box.value = 1;
+        box.value = box.value + 1;
+        print box.value;
+    ");
+    assert_eq!(printed, ["2"]);
+}
+
+#[test]
+fn methods_see_their_instance_as_this() {
+    let printed = run("
+        class Counter {
+            increment() {
+                this.count = this.count + 1;
+                return this;
+            }
+        }
+        var counter = Counter();
+        counter.count = 0;
+        counter.increment().increment();
+        print counter.count;
+    ");
+    assert_eq!(printed, ["2"]);
+}
+
+#[test]
+fn bound_methods_remember_their_instance() {
+    let printed = run("
+        class Greeter {
+            greet() {
+                print \"hello, \" + this.name;
+            }
+        }
+        var greeter = Greeter();
+        greeter.name = \"lox\";
+        var greet = greeter.greet;
+        greeter.name = \"world\";
+        greet();
+    ");
+    assert_eq!(printed, ["hello, world"]);
+}
+
+#[test]
+fn init_runs_on_construction_and_returns_the_instance() {
+    let printed = run("
+        class Point {
+            init(x, y) {
+                this.x = x;
+                this.y = y;
+                if (x == 0) return;
+                this.far = true;
+            }
+        }
+        var point = Point(1, 2);
+        print point.x + point.y;
+        print point.far;
+        print point.init(0, 0) == point;
+        print point.x;
+    ");
+    assert_eq!(printed, ["3", "true", "true", "0"]);
+}
+
+#[test]
+fn fields_shadow_methods() {
+    let printed = run("
+        class Thing {
+            name() { return \"method\"; }
+        }
+        fun field() { return \"field\"; }
+        var thing = Thing();
+        thing.name = field;
+        print thing.name();
+    ");
+    assert_eq!(printed, ["field"]);
+}
+
+#[test]
+fn constructors_check_their_arity() {
+    let (_, error) = run_err(
+        "
+        class Point {
+            init(x, y) {}
+        }
+        Point(1);
+    ",
+    );
+    assert_eq!(error.message(), "Expected 2 arguments but got 1.");
+}
+
+#[test]
+fn undefined_properties_are_errors() {
+    let (_, error) = run_err(
+        "
+        class Empty {}
+        print Empty().missing;
+    ",
+    );
+    assert_eq!(error.message(), "Undefined property 'missing'.");
+    assert_eq!(error.span().line, 3);
+}
+
+#[test]
+fn only_instances_have_properties() {
+    let (_, error) = run_err("var number = 1; print number.field;");
+    assert_eq!(
+        error.message(),
+        "Only instances and modules have properties."
+    );
+}
+
+#[test]
+fn this_outside_a_class_is_a_compile_error() {
+    let (_, error) = run_err("print this;");
+    assert!(matches!(error, LoxError::Resolve { .. }));
+    assert_eq!(error.message(), "Can't use 'this' outside of a class.");
+}
+
+#[test]
+fn initializers_cannot_return_values() {
+    let (_, error) = run_err(
+        "
+        class Point {
+            init() { return 1; }
+        }
+    ",
+    );
+    assert_eq!(error.message(), "Can't return a value from an initializer.");
+}
@@ -0,0 +1,42 @@
+// Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::{cell::RefCell, rc::Rc};
+
+use rubrs::{Capabilities, Interpreter, LoxError, Output};
+
+/// Everything a script printed, one entry per `print`.
+pub type Printed = Rc<RefCell<Vec<String>>>;
+
+/// An interpreter whose printed output is captured instead of written to
+/// stdout.
+pub fn interpreter(capabilities: Capabilities) -> (Interpreter, Printed) {
+    let printed = Printed::default();
+    let sink = printed.clone();
+    let interpreter = Interpreter::builder()
+        .capabilities(capabilities)
+        .output(Output::callback(move |text| {
+            sink.borrow_mut().push(text.to_string())
+        }))
+        .build();
+    (interpreter, printed)
+}
+
+/// Runs `code`, which must succeed, and returns what it printed.
+pub fn run(code: &str) -> Vec<String> {
+    let (interpreter, printed) = interpreter(Capabilities::none());
+    if let Err(errors) = interpreter.parse_and_run(code) {
+        panic!("script failed: {}", errors[0]);
+    }
+    printed.take()
+}
+
+/// Runs `code`, which must fail, and returns what it printed before failing
+/// along with the error.
+pub fn run_err(code: &str) -> (Vec<String>, LoxError) {
+    let (interpreter, printed) = interpreter(Capabilities::none());
+    match interpreter.parse_and_run(code) {
+        Ok(()) => panic!("script succeeded, printing {:?}", printed.take()),
+        Err(mut errors) => (printed.take(), errors.remove(0)),
+    }
+}
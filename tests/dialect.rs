@@ -0,0 +1,91 @@
+mod common;
+
+use common::{run, Printed};
+use rubrs::{Dialect, Interpreter, Output};
+
+/// Exercises every construct that tests truthiness with `0` and `""`.
+const TRUTHINESS: &str = "
+    if (0) print \"0 is truthy\"; else print \"0 is falsey\";
+    if (\"\") print \"empty is truthy\"; else print \"empty is falsey\";
+    print !0;
+    print !\"\";
+    print 0 or \"fallback\";
+    print \"\" and \"second\";
+    var i = 3;
+    while (i) i = i - 1;
+    print i;
+";
+
+/// Like [`run`], but with the permissive dialect.
+fn run_permissive(code: &str) -> Vec<String> {
+    let printed = Printed::default();
+    let sink = printed.clone();
+    let interpreter = Interpreter::builder()
+        .dialect(Dialect::Permissive)
+        .output(Output::callback(move |text| {
+            sink.borrow_mut().push(text.to_string())
+        }))
+        .build();
+    if let Err(errors) = interpreter.parse_and_run(code) {
+        panic!("script failed: {}", errors[0]);
+    }
+    printed.take()
+}
+
+#[test]
+fn only_nil_and_false_are_falsey_by_default() {
+    let printed = run("
+        if (0) print \"0 is truthy\";
+        if (\"\") print \"empty is truthy\";
+        print !0;
+        print !\"\";
+        print 0 or \"fallback\";
+        print \"\" and \"second\";
+        print !nil;
+        print !false;
+    ");
+    assert_eq!(
+        printed,
+        [
+            "0 is truthy",
+            "empty is truthy",
+            "false",
+            "false",
+            "0",
+            "second",
+            "true",
+            "true"
+        ]
+    );
+}
+
+#[test]
+fn permissive_dialect_treats_zero_and_empty_strings_as_falsey() {
+    let printed = run_permissive(TRUTHINESS);
+    assert_eq!(
+        printed,
+        [
+            "0 is falsey",
+            "empty is falsey",
+            "true",
+            "true",
+            "fallback",
+            "",
+            "0"
+        ]
+    );
+}
+
+#[test]
+fn permissive_dialect_keeps_other_values_truthy() {
+    let printed = run_permissive(
+        "
+        for (var n = 2; n; n = n - 1) print n;
+        print !\"0\";
+        print ![];
+        print !-0.5;
+        print nil or false or 0 or \"last\";
+    ",
+    );
+    assert_eq!(printed, ["2", "1", "false", "false", "false", "last"]);
+}
@@ -0,0 +1,85 @@
+use std::process::Command;
+
+use rubrs::dump;
+
+#[test]
+fn tokens_are_listed_with_their_positions() {
+    let tokens = dump::tokens("print 1.5;\nvar s = \"hi\";").unwrap();
+    assert_eq!(
+        tokens,
+        "[1:1] Print print\n\
+         [1:7] Number(1.5) 1.5\n\
+         [1:10] Semicolon ;\n\
+         [2:1] Var var\n\
+         [2:5] Identifier s\n\
+         [2:7] Equal =\n\
+         [2:9] String(\"hi\") \"hi\"\n\
+         [2:13] Semicolon ;\n\
+         [2:14] Eof \n"
+    );
+}
+
+#[test]
+fn statements_are_printed_as_s_expressions() {
+    let ast = dump::ast(
+        "
+        class A < B { m() { return super.m(this.x[0]); } }
+        try { throw \"x\"; } catch (e) { print -e; }
+        var m = {\"a\": [1, 2]};
+        m[\"a\"] = nil;
+        var f = fun (x) => x;
+    ",
+    )
+    .unwrap();
+    assert_eq!(
+        ast,
+        "(class A < B (fun m () (return ((. super m) ([] (. this x) 0)))))\n\
+         (try (block (throw \"x\")) (catch e (print (- e))))\n\
+         (var m (map (\"a\" (list 1 2))))\n\
+         (; (= ([] m \"a\") nil))\n\
+         (var f (fun (x) (return x)))\n"
+    );
+}
+
+#[test]
+fn for_loops_are_shown_desugared() {
+    let ast = dump::ast("for (var i = 0; i < 2; i = i + 1) print i;").unwrap();
+    assert_eq!(
+        ast,
+        "(block (var i 0) (while (< i 2) (print i) (i = (+ i 1))))\n"
+    );
+}
+
+#[test]
+fn dumping_reports_syntax_errors() {
+    let errors = dump::ast("print (;").unwrap_err();
+    assert_eq!(errors[0].message(), "Expect expression.");
+
+    let errors = dump::tokens("print \"open;").unwrap_err();
+    assert_eq!(errors[0].message(), "Unterminated string.");
+}
+
+#[test]
+fn the_cli_dumps_instead_of_running() {
+    let script = std::env::temp_dir().join(format!("rubrs-dump-{}.lox", std::process::id()));
+    std::fs::write(&script, "print 1 + 2;").unwrap();
+    let rubrs = |flags: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rubrs"))
+            .args(flags)
+            .arg(&script)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let ast = rubrs(&["--dump-ast"]);
+    let tokens = rubrs(&["--dump-tokens"]);
+    let run = rubrs(&[]);
+    std::fs::remove_file(&script).unwrap();
+
+    assert_eq!(ast, "(print (+ 1 2))\n");
+    assert!(tokens.starts_with("[1:1] Print print\n"), "{}", tokens);
+    assert!(tokens.ends_with("[1:13] Eof \n"), "{}", tokens);
+    assert_eq!(run, "3\n");
+}
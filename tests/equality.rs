@@ -0,0 +1,69 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn values_of_the_same_type_compare_by_value() {
+    let printed = run("
+        print 1 == 1.0;
+        print \"a\" + \"b\" == \"ab\";
+        print true == true;
+        print nil == nil;
+        print 1 != 2;
+        print \"a\" != \"a\";
+    ");
+    assert_eq!(printed, ["true", "true", "true", "true", "true", "false"]);
+}
+
+#[test]
+fn values_of_different_types_are_never_equal() {
+    let printed = run("
+        print 0 == false;
+        print nil == false;
+        print \"1\" == 1;
+        print \"\" == nil;
+        print 0 != \"0\";
+    ");
+    assert_eq!(printed, ["false", "false", "false", "false", "true"]);
+}
+
+#[test]
+fn nan_is_not_equal_to_itself() {
+    let printed = run("
+        var nan = 0 / 0;
+        print nan == nan;
+        print nan != nan;
+        print -0 == 0;
+    ");
+    assert_eq!(printed, ["false", "true", "true"]);
+}
+
+#[test]
+fn functions_are_equal_only_to_themselves() {
+    let printed = run("
+        fun make() {
+            fun count() {}
+            return count;
+        }
+        var first = make();
+        var second = make();
+        print first == first;
+        print first == second;
+        print len == len;
+        print len == push;
+    ");
+    assert_eq!(printed, ["true", "false", "true", "false"]);
+}
+
+#[test]
+fn classes_and_instances_are_equal_only_to_themselves() {
+    let printed = run("
+        class Point {}
+        var a = Point();
+        var b = Point();
+        print Point == Point;
+        print a == a;
+        print a == b;
+    ");
+    assert_eq!(printed, ["true", "true", "false"]);
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn catch_receives_the_thrown_value() {
+    let printed = run("
+        try {
+            throw \"boom\";
+        } catch (e) {
+            print e;
+        }
+    ");
+    assert_eq!(printed, ["boom"]);
+}
+
+#[test]
+fn runtime_errors_are_caught_as_error_values() {
+    let printed = run("
+        try {
+            print 1 + nil;
+        } catch (e) {
+            print e.message;
+            print e.line;
+        }
+    ");
+    assert_eq!(
+        printed,
+        ["Operands must be two numbers or two strings.", "3"]
+    );
+}
+
+#[test]
+fn finally_runs_when_returning_through_it() {
+    let printed = run("
+        fun f() {
+            try {
+                return \"try\";
+            } finally {
+                print \"finally\";
+            }
+        }
+        print f();
+    ");
+    assert_eq!(printed, ["finally", "try"]);
+}
+
+#[test]
+fn finally_runs_while_an_error_unwinds_to_an_outer_catch() {
+    let printed = run("
+        fun inner() {
+            try {
+                throw \"inner\";
+            } finally {
+                print \"inner finally\";
+            }
+            print \"unreachable\";
+        }
+        try {
+            try {
+                inner();
+            } finally {
+                print \"middle finally\";
+            }
+        } catch (e) {
+            print \"caught \" + e;
+        }
+    ");
+    assert_eq!(printed, ["inner finally", "middle finally", "caught inner"]);
+}
+
+#[test]
+fn finally_runs_on_break_and_continue() {
+    let printed = run("
+        for (var i = 0; i < 3; i = i + 1) {
+            try {
+                if (i == 0) continue;
+                if (i == 2) break;
+                print i;
+            } finally {
+                print \"finally\";
+            }
+        }
+    ");
+    assert_eq!(printed, ["finally", "1", "finally", "finally"]);
+}
+
+#[test]
+fn a_return_in_finally_replaces_the_error() {
+    let printed = run("
+        fun f() {
+            try {
+                throw \"lost\";
+            } finally {
+                return \"finally\";
+            }
+        }
+        print f();
+    ");
+    assert_eq!(printed, ["finally"]);
+}
+
+#[test]
+fn errors_thrown_in_catch_reach_the_outer_try() {
+    let printed = run("
+        try {
+            try {
+                throw \"first\";
+            } catch (e) {
+                throw Error(\"rethrown \" + e);
+            }
+        } catch (e) {
+            print e.message;
+        }
+    ");
+    assert_eq!(printed, ["rethrown first"]);
+}
+
+#[test]
+fn uncaught_throws_fail_the_script_with_the_value() {
+    let (printed, error) = run_err(
+        "
+        try {
+            print \"before\";
+            throw \"escaped\";
+        } finally {
+            print \"finally\";
+        }
+    ",
+    );
+    assert_eq!(printed, ["before", "finally"]);
+    assert_eq!(
+        error.thrown().map(ToString::to_string).as_deref(),
+        Some("escaped")
+    );
+}
+
+#[test]
+fn stack_overflow_skips_catch_and_finally() {
+    let (printed, error) = run_err(
+        "
+        fun f() { return f(); }
+        try {
+            f();
+        } catch (e) {
+            print \"caught\";
+        } finally {
+            print \"finally\";
+        }
+    ",
+    );
+    assert!(printed.is_empty());
+    assert_eq!(error.message(), "Stack overflow.");
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn subclasses_inherit_methods() {
+    let printed = run("
+        class Animal {
+            speak() { return \"...\"; }
+            describe() { return this.name + \" says \" + this.speak(); }
+        }
+        class Dog < Animal {
+            speak() { return \"woof\"; }
+        }
+        var dog = Dog();
+        dog.name = \"Rex\";
+        print dog.describe();
+    ");
+    assert_eq!(printed, ["Rex says woof"]);
+}
+
+#[test]
+fn super_calls_the_superclass_method_on_this_instance() {
+    let printed = run("
+        class Base {
+            init(name) { this.name = name; }
+            greet() { return \"hello from \" + this.name; }
+        }
+        class Derived < Base {
+            init(name) { super.init(name + \"!\"); }
+            greet() { return super.greet() + \" (derived)\"; }
+        }
+        print Derived(\"d\").greet();
+    ");
+    assert_eq!(printed, ["hello from d! (derived)"]);
+}
+
+#[test]
+fn super_is_resolved_from_the_class_the_method_is_in() {
+    let printed = run("
+        class A {
+            method() { return \"A\"; }
+        }
+        class B < A {
+            method() { return \"B\"; }
+            test() { return super.method(); }
+        }
+        class C < B {}
+        print C().test();
+    ");
+    assert_eq!(printed, ["A"]);
+}
+
+#[test]
+fn inherited_initializers_run() {
+    let printed = run("
+        class Base {
+            init(value) { this.value = value; }
+        }
+        class Derived < Base {}
+        print Derived(3).value;
+    ");
+    assert_eq!(printed, ["3"]);
+}
+
+#[test]
+fn superclasses_must_be_classes() {
+    let (_, error) = run_err(
+        "
+        var NotAClass = \"nope\";
+        class Derived < NotAClass {}
+    ",
+    );
+    assert_eq!(
+        error.message(),
+        "Superclass 'NotAClass' of 'Derived' must be a class."
+    );
+}
+
+#[test]
+fn missing_super_methods_name_the_superclass() {
+    let (_, error) = run_err(
+        "
+        class Base {}
+        class Derived < Base {
+            method() { return super.missing(); }
+        }
+        Derived().method();
+    ",
+    );
+    assert_eq!(
+        error.message(),
+        "Undefined method 'missing' on superclass 'Base'."
+    );
+}
+
+#[test]
+fn misuses_of_super_are_compile_errors() {
+    for (code, message) in [
+        ("class A < A {}", "A class can't inherit from itself."),
+        (
+            "class A { method() { super.method(); } }",
+            "Can't use 'super' in a class with no superclass.",
+        ),
+        ("super.method();", "Can't use 'super' outside of a class."),
+    ] {
+        let (_, error) = run_err(code);
+        assert_eq!(error.message(), message);
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{run, run_err};
+use rubrs::LoxError;
+
+#[test]
+fn lambdas_are_values() {
+    let printed = run("
+        var double = fun (x) { return x * 2; };
+        var square = fun (x) => x * x;
+        print double(4);
+        print square(5);
+        print fun () => \"now\";
+        print (fun (a, b) => a + b)(1, 2);
+    ");
+    assert_eq!(printed, ["8", "25", "<fn anonymous>", "3"]);
+}
+
+#[test]
+fn lambdas_can_be_passed_as_callbacks() {
+    let printed = run("
+        fun map(items, f) {
+            var result = [];
+            for (var i = 0; i < len(items); i = i + 1) push(result, f(items[i]));
+            return result;
+        }
+
+        print map([1, 2, 3], fun (x) => x * 10);
+        print map([\"a\", \"b\"], fun (s) { return s + s; });
+    ");
+    assert_eq!(printed, ["[10, 20, 30]", "[\"aa\", \"bb\"]"]);
+}
+
+#[test]
+fn lambdas_capture_their_enclosing_scope() {
+    let printed = run("
+        fun counter() {
+            var count = 0;
+            return fun () {
+                count = count + 1;
+                return count;
+            };
+        }
+
+        var a = counter();
+        var b = counter();
+        a();
+        a();
+        print a();
+        print b();
+
+        var adders = [];
+        for (var i = 1; i <= 2; i = i + 1) {
+            var n = i;
+            push(adders, fun (x) => x + n);
+        }
+        print adders[0](10);
+        print adders[1](10);
+    ");
+    assert_eq!(printed, ["3", "1", "11", "12"]);
+}
+
+#[test]
+fn lambdas_check_their_arity() {
+    let (_, error) = run_err("(fun (x) => x)(1, 2);");
+    assert_eq!(error.message(), "Expected 1 arguments but got 2.");
+}
+
+#[test]
+fn arrow_lambdas_take_a_single_expression() {
+    let (_, error) = run_err("var f = fun () => { return 1; };");
+    assert!(matches!(error, LoxError::Parse { .. }), "{:?}", error);
+}
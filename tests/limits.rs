@@ -0,0 +1,92 @@
+mod common;
+
+use common::{run, run_err};
+use rubrs::Interpreter;
+
+const COUNT_DOWN: &str = "
+    fun count(n) {
+        if (n == 0) return 0;
+        return 1 + count(n - 1);
+    }
+";
+
+#[test]
+fn ordinary_recursion_fits_in_the_default_limits() {
+    let printed = run(&format!("{} print count(100);", COUNT_DOWN));
+    assert_eq!(printed, ["100"]);
+}
+
+#[test]
+fn max_call_depth_is_the_limit() {
+    let interpreter = Interpreter::builder().max_call_depth(50).build();
+    interpreter.eval(COUNT_DOWN).unwrap();
+    assert!(interpreter.eval("count(49)").is_ok());
+
+    let errors = interpreter.eval("count(50)").unwrap_err();
+    assert_eq!(errors[0].message(), "Stack overflow.");
+}
+
+#[test]
+fn running_out_of_stack_is_an_error() {
+    // Deeper than the stack of the thread running the test has room for.
+    let interpreter = Interpreter::builder().max_call_depth(usize::MAX).build();
+    interpreter.eval(COUNT_DOWN).unwrap();
+
+    let errors = interpreter.eval("count(1000000)").unwrap_err();
+    assert_eq!(errors[0].message(), "Stack overflow.");
+}
+
+#[test]
+fn long_operator_chains_are_not_nesting() {
+    let sum = vec!["1"; 10_000].join(" + ");
+    assert_eq!(run(&format!("print {};", sum)), ["10000"]);
+
+    let all = vec!["true"; 10_000].join(" and ");
+    assert_eq!(run(&format!("print {};", all)), ["true"]);
+}
+
+#[test]
+fn long_call_and_property_chains_are_not_nesting() {
+    let printed = run(&format!(
+        "
+        fun f() {{ return f; }}
+        print f{};
+
+        class Node {{ init() {{ this.next = this; }} }}
+        var node = Node();
+        print node{} == node;
+        ",
+        "()".repeat(10_000),
+        ".next".repeat(10_000),
+    ));
+    assert_eq!(printed, ["<fn f>", "true"]);
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let (_, error) = run_err(&format!("print {}1{};", "(".repeat(1000), ")".repeat(1000)));
+    assert_eq!(error.message(), "Too much nesting.");
+}
+
+#[test]
+fn deeply_nested_values_print_and_drop() {
+    let printed = run("
+        class Node {}
+        var list = nil;
+        var map = nil;
+        var node = nil;
+        for (var i = 0; i < 100000; i = i + 1) {
+            list = [list];
+            map = {\"next\": map};
+            var next = Node();
+            next.next = node;
+            node = next;
+        }
+        print list;
+        print map;
+    ");
+
+    let list = format!("{}[...]{}", "[".repeat(100), "]".repeat(100));
+    let map = format!("{}{{...}}{}", "{\"next\": ".repeat(100), "}".repeat(100));
+    assert_eq!(printed, [list, map]);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn list_natives() {
+    let printed = run("
+        var list = [1, 2];
+        push(list, 3);
+        print list;
+        print pop(list);
+        insert(list, 0, 0);
+        insert(list, len(list), 9);
+        print list;
+        print remove(list, 1);
+        print list;
+        print len(list);
+    ");
+    assert_eq!(
+        printed,
+        ["[1, 2, 3]", "3", "[0, 1, 2, 9]", "1", "[0, 2, 9]", "3"]
+    );
+}
+
+#[test]
+fn negative_indices_count_from_the_end() {
+    let printed = run("
+        var list = [1, 2, 3];
+        print list[-1];
+        list[-2] = 5;
+        print list;
+        print remove(list, -3);
+    ");
+    assert_eq!(printed, ["3", "[1, 5, 3]", "1"]);
+}
+
+#[test]
+fn lists_are_shared_by_reference() {
+    let printed = run("
+        var a = [];
+        var b = a;
+        push(b, \"x\");
+        print a;
+    ");
+    assert_eq!(printed, ["[\"x\"]"]);
+}
+
+#[test]
+fn len_counts_characters_of_strings() {
+    assert_eq!(run("print len(\"héllo\");"), ["5"]);
+}
+
+#[test]
+fn pop_from_an_empty_list_is_an_error() {
+    let (_, error) = run_err("pop([]);");
+    assert_eq!(error.message(), "Can't pop from an empty list.");
+}
+
+#[test]
+fn len_checks_its_argument_type() {
+    let (_, error) = run_err("len(1);");
+    assert_eq!(
+        error.message(),
+        "Argument 1: expected list, map or string but got number."
+    );
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn continue_in_a_for_loop_runs_the_increment() {
+    let printed = run("
+        for (var i = 0; i < 5; i = i + 1) {
+            if (i == 1 or i == 3) continue;
+            print i;
+        }
+    ");
+    assert_eq!(printed, ["0", "2", "4"]);
+}
+
+#[test]
+fn continue_in_a_nested_block_runs_the_increment() {
+    let printed = run("
+        for (var i = 0; i < 3; i = i + 1) {
+            {
+                var skip = i == 1;
+                if (skip) {
+                    continue;
+                }
+            }
+            print i;
+        }
+    ");
+    assert_eq!(printed, ["0", "2"]);
+}
+
+#[test]
+fn continue_only_affects_the_innermost_loop() {
+    let printed = run("
+        for (var i = 0; i < 2; i = i + 1) {
+            for (var j = 0; j < 3; j = j + 1) {
+                if (j == 1) continue;
+                print i * 10 + j;
+            }
+        }
+    ");
+    assert_eq!(printed, ["0", "2", "10", "12"]);
+}
+
+#[test]
+fn continue_in_a_while_loop_rechecks_the_condition() {
+    let printed = run("
+        var i = 0;
+        while (i < 4) {
+            i = i + 1;
+            if (i == 2) continue;
+            print i;
+        }
+    ");
+    assert_eq!(printed, ["1", "3", "4"]);
+}
+
+#[test]
+fn break_leaves_the_loop() {
+    let printed = run("
+        for (var i = 0; ; i = i + 1) {
+            if (i == 2) break;
+            print i;
+        }
+        print \"done\";
+    ");
+    assert_eq!(printed, ["0", "1", "done"]);
+}
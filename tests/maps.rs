@@ -0,0 +1,36 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn map_natives() {
+    let printed = run("
+        var map = {\"a\": 1, \"b\": 2};
+        map[\"c\"] = 3;
+        print len(map);
+        print keys(map);
+        print values(map);
+        print has(map, \"b\");
+        print remove(map, \"b\");
+        print has(map, \"b\");
+        print map;
+    ");
+    assert_eq!(
+        printed,
+        [
+            "3",
+            "[\"a\", \"b\", \"c\"]",
+            "[1, 2, 3]",
+            "true",
+            "2",
+            "false",
+            "{\"a\": 1, \"c\": 3}",
+        ]
+    );
+}
+
+#[test]
+fn map_natives_check_their_argument_types() {
+    let (_, error) = run_err("keys([1]);");
+    assert_eq!(error.message(), "Argument 1: expected map but got list.");
+}
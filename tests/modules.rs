@@ -0,0 +1,111 @@
+mod common;
+
+use rubrs::{Capabilities, LoxError};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/modules");
+
+/// Runs `code` as if it were a script in the module fixtures directory, and
+/// returns what it printed along with the result.
+fn run_in_fixtures(code: &str) -> (Vec<String>, Result<(), Vec<LoxError>>) {
+    let (interpreter, printed) = common::interpreter(Capabilities {
+        filesystem: true,
+        ..Capabilities::none()
+    });
+    let path = format!("{}/main.lox", FIXTURES);
+    let result = interpreter.parse_and_run_at(&path, code);
+    (printed.take(), result)
+}
+
+fn run(code: &str) -> Vec<String> {
+    let (printed, result) = run_in_fixtures(code);
+    if let Err(errors) = result {
+        panic!("script failed: {}", errors[0]);
+    }
+    printed
+}
+
+fn run_err(code: &str) -> LoxError {
+    match run_in_fixtures(code) {
+        (printed, Ok(())) => panic!("script succeeded, printing {:?}", printed),
+        (_, Err(mut errors)) => errors.remove(0),
+    }
+}
+
+#[test]
+fn modules_run_once_and_share_their_state() {
+    let printed = run("
+        import \"counter.lox\" as first;
+        import \"counter.lox\" as second;
+        from \"counter.lox\" import increment;
+        import \"nested/user.lox\" as user;
+
+        print first.increment();
+        print second.increment();
+        print increment();
+        print user.bump();
+        print first.count;
+    ");
+    assert_eq!(printed, ["loading counter", "1", "2", "3", "4", "4"]);
+}
+
+#[test]
+fn import_cycles_are_errors() {
+    let error = run_err("import \"cycle_a.lox\" as a;");
+    assert_eq!(
+        error.message(),
+        format!(
+            "Circular import: {0}/cycle_a.lox -> {0}/cycle_b.lox -> {0}/cycle_a.lox.",
+            FIXTURES
+        )
+    );
+}
+
+#[test]
+fn a_failed_import_can_be_caught() {
+    let printed = run("
+        try {
+            import \"broken.lox\" as broken;
+        } catch (e) {
+            print e.message;
+        }
+        try {
+            import \"missing.lox\" as missing;
+        } catch (e) {
+            print \"missing\";
+        }
+    ");
+    assert_eq!(printed, ["Expect expression.", "missing"]);
+}
+
+#[test]
+fn assigning_to_a_builtin_only_shadows_it_in_that_module() {
+    let printed = run("
+        import \"shadow.lox\" as shadow;
+        print shadow.local;
+        print len(\"abc\");
+    ");
+    assert_eq!(printed, ["shadowed", "3"]);
+}
+
+#[test]
+fn as_and_from_are_not_reserved() {
+    let printed = run("
+        var from = \"from\";
+        import \"counter.lox\" as as;
+        print from;
+        print as.increment();
+    ");
+    assert_eq!(printed, ["loading counter", "from", "1"]);
+}
+
+#[test]
+fn importing_needs_filesystem_access() {
+    let (interpreter, _) = common::interpreter(Capabilities::none());
+    let errors = interpreter
+        .parse_and_run("import \"counter.lox\" as counter;")
+        .unwrap_err();
+    assert_eq!(
+        errors[0].message(),
+        "Can't import 'counter.lox': filesystem access is disabled."
+    );
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::interpreter;
+use rubrs::{Capabilities, LoxError};
+
+/// Compiles and runs `code`, which must fail, and returns what it printed
+/// along with every error.
+fn errors(code: &str) -> (Vec<String>, Vec<LoxError>) {
+    let (interpreter, printed) = interpreter(Capabilities::none());
+    let errors = interpreter.parse_and_run(code).unwrap_err();
+    (printed.take(), errors)
+}
+
+fn summary(errors: &[LoxError]) -> Vec<(usize, &str)> {
+    errors
+        .iter()
+        .map(|error| (error.span().line, error.message()))
+        .collect()
+}
+
+#[test]
+fn every_syntax_error_is_reported() {
+    let (printed, errors) = errors(
+        "print \"before\";
+         var = 1;
+         print 1 2;
+         fun f( {}
+         class {}
+         print \"after\";",
+    );
+
+    assert!(printed.is_empty());
+    assert_eq!(
+        summary(&errors),
+        [
+            (2, "Expect variable name."),
+            (3, "Expect ';' after value."),
+            (4, "Expect parameter name."),
+            (5, "Expect class name."),
+        ]
+    );
+}
+
+#[test]
+fn errors_inside_blocks_and_functions_are_recovered_from() {
+    let (_, errors) = errors(
+        "fun f() {
+             var = 1;
+             print 1 2;
+         }
+         while (true) {
+             print;
+         }
+         var ok = 1 +;",
+    );
+
+    assert_eq!(
+        summary(&errors),
+        [
+            (2, "Expect variable name."),
+            (3, "Expect ';' after value."),
+            (6, "Expect expression."),
+            (8, "Expect expression."),
+        ]
+    );
+}
+
+#[test]
+fn invalid_assignment_targets_do_not_stop_parsing() {
+    let (_, errors) = errors(
+        "1 = 2;
+         print 1 2;",
+    );
+
+    assert_eq!(
+        summary(&errors),
+        [
+            (1, "Invalid assignment target."),
+            (2, "Expect ';' after value."),
+        ]
+    );
+}
+
+#[test]
+fn every_resolve_error_is_reported() {
+    let (_, errors) = errors(
+        "{
+             var a = 1;
+             var a = 2;
+         }
+         print this;",
+    );
+
+    assert!(errors
+        .iter()
+        .all(|error| matches!(error, LoxError::Resolve { .. })));
+    assert_eq!(
+        summary(&errors),
+        [
+            (3, "Already a variable with this name in this scope."),
+            (5, "Can't use 'this' outside of a class."),
+        ]
+    );
+}
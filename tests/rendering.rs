@@ -0,0 +1,110 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rubrs::{Interpreter, Output, Renderer};
+
+fn render(path: &str, source: &str) -> String {
+    let errors = Interpreter::new().parse_and_run(source).unwrap_err();
+    Renderer::new(path, source).color(false).render(&errors[0])
+}
+
+#[test]
+fn errors_are_shown_under_the_code_they_point_at() {
+    assert_eq!(
+        render("script.lox", "var a = 1;\nprint a 2;"),
+        "parse error: Expect ';' after value.\n \
+         --> script.lox:2:9\n  \
+         |\n\
+         2 | print a 2;\n  \
+         |         ^"
+    );
+}
+
+#[test]
+fn underlines_line_up_after_wide_characters() {
+    assert_eq!(
+        render("script.lox", "print \"ééé\" - 1;"),
+        "runtime error: Operands must be numbers.\n \
+         --> script.lox:1:13\n  \
+         |\n\
+         1 | print \"ééé\" - 1;\n  \
+         |             ^"
+    );
+}
+
+#[test]
+fn errors_from_other_code_are_shown_without_a_snippet() {
+    let interpreter = Interpreter::new();
+    let errors = interpreter
+        .parse_and_run("fun f() { return 1 + nil; } f();")
+        .unwrap_err();
+
+    // The error's offset falls inside a character of this source.
+    let rendered = Renderer::new("<repl:2>", "print \"xéééééééééé\";")
+        .color(false)
+        .render(&errors[0]);
+    assert!(rendered.starts_with("runtime error: Operands must be two numbers or two strings.\n"));
+    assert!(!rendered.contains("print"));
+}
+
+#[test]
+fn named_snippets_keep_their_source() {
+    let reported = Rc::new(RefCell::new(Vec::new()));
+    let sink = reported.clone();
+    let interpreter = Interpreter::builder()
+        .error_output(Output::callback(move |text| {
+            sink.borrow_mut().push(text.to_string())
+        }))
+        .build();
+
+    let first = "fun f() { return 1 + nil; }";
+    interpreter.parse_and_run_named("<repl:1>", first).unwrap();
+    let second = "print \"éééééééééééé\" + f();";
+    let errors = interpreter
+        .parse_and_run_named("<repl:2>", second)
+        .unwrap_err();
+    interpreter.report(&Renderer::new("<repl:2>", second).color(false), &errors);
+
+    let reported = reported.borrow();
+    assert!(reported[0].contains(" --> <repl:1>:1:20\n"));
+    assert!(reported[0].contains("1 | fun f() { return 1 + nil; }\n"));
+}
+
+#[test]
+fn help_and_backtraces_follow_the_snippet() {
+    let source = "class A {\n  init() { return 1; }\n}";
+    assert_eq!(
+        render("a.lox", source),
+        "resolve error: Can't return a value from an initializer.\n \
+         --> a.lox:2:12\n  \
+         |\n\
+         2 |   init() { return 1; }\n  \
+         |            ^^^^^^\n  \
+         = help: 'init' always returns 'this'; use a bare 'return;'"
+    );
+
+    let source = "fun f() {\n  return -nil;\n}\nf();";
+    assert_eq!(
+        render("f.lox", source),
+        "runtime error: Operand must be a number.\n \
+         --> f.lox:2:10\n  \
+         |\n\
+         2 |   return -nil;\n  \
+         |          ^\n  \
+         = backtrace:\n      \
+         [line 2] in f()\n      \
+         [line 4] in script"
+    );
+}
+
+#[test]
+fn tabs_are_kept_in_the_padding() {
+    let rendered = render("t.lox", "\tprint\t1 2;");
+    assert!(rendered.ends_with("1 | \tprint\t1 2;\n  | \t     \t  ^"));
+}
+
+#[test]
+fn colors_can_be_turned_on() {
+    let errors = Interpreter::new().parse_and_run("print 1 2;").unwrap_err();
+    let rendered = Renderer::new("c.lox", "print 1 2;").render(&errors[0]);
+    assert!(rendered.starts_with("\x1b[1;31mparse error\x1b[0m"));
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn return_inside_if_leaves_the_function() {
+    let printed = run("
+        fun sign(n) {
+            if (n < 0) {
+                return \"negative\";
+            } else if (n == 0) {
+                return \"zero\";
+            }
+            return \"positive\";
+        }
+        print sign(-2);
+        print sign(0);
+        print sign(5);
+    ");
+    assert_eq!(printed, ["negative", "zero", "positive"]);
+}
+
+#[test]
+fn return_inside_while_stops_the_loop() {
+    let printed = run("
+        fun firstOver(limit) {
+            var i = 0;
+            while (true) {
+                i = i + 1;
+                if (i * i > limit) return i;
+                print i;
+            }
+        }
+        print firstOver(5);
+    ");
+    assert_eq!(printed, ["1", "2", "3"]);
+}
+
+#[test]
+fn return_inside_for_and_nested_blocks() {
+    let printed = run("
+        fun find(target) {
+            for (var i = 0; i < 10; i = i + 1) {
+                {
+                    var doubled = i * 2;
+                    if (doubled == target) {
+                        return i;
+                    }
+                }
+            }
+            return nil;
+        }
+        print find(6);
+        print find(7);
+    ");
+    assert_eq!(printed, ["3", "nil"]);
+}
+
+#[test]
+fn returned_values_keep_their_type() {
+    let printed = run("
+        fun pick(n) {
+            if (n == 1) return 1;
+            if (n == 2) return \"two\";
+            if (n == 3) return true;
+            return;
+        }
+        print pick(1) + 1;
+        print pick(2) + \"!\";
+        print !pick(3);
+        print pick(4);
+    ");
+    assert_eq!(printed, ["2", "two!", "false", "nil"]);
+}
+
+#[test]
+fn return_only_leaves_the_innermost_function() {
+    let printed = run("
+        fun outer() {
+            fun inner() {
+                while (true) return \"inner\";
+            }
+            var value = inner();
+            return value + \" then outer\";
+        }
+        print outer();
+    ");
+    assert_eq!(printed, ["inner then outer"]);
+}
+
+#[test]
+fn top_level_return_is_a_compile_error() {
+    let (_, error) = run_err("return 1;");
+    assert_eq!(error.message(), "Can't return from top-level code.");
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::run;
+
+// Locals live in slots at the index the resolver gave them, so these check
+// that the interpreter defines them in the same order the resolver declares
+// them.
+
+#[test]
+fn locals_in_a_block_keep_their_own_slots() {
+    let printed = run("
+        {
+            var a = \"a\";
+            var b = \"b\";
+            var c = a + b;
+            print a;
+            print b;
+            print c;
+        }
+    ");
+    assert_eq!(printed, ["a", "b", "ab"]);
+}
+
+#[test]
+fn parameters_come_before_body_locals() {
+    let printed = run("
+        fun f(x, y) {
+            var z = x + y;
+            var w = z * 2;
+            print x;
+            print y;
+            print z;
+            print w;
+        }
+        f(1, 2);
+    ");
+    assert_eq!(printed, ["1", "2", "3", "6"]);
+}
+
+#[test]
+fn closures_capture_the_right_slot() {
+    let printed = run("
+        fun counter() {
+            var unused = \"unused\";
+            var count = 0;
+            fun increment() {
+                count = count + 1;
+                return count;
+            }
+            return increment;
+        }
+        var next = counter();
+        next();
+        print next();
+        print counter()();
+    ");
+    assert_eq!(printed, ["2", "1"]);
+}
+
+#[test]
+fn shadowing_in_nested_blocks() {
+    let printed = run("
+        var a = \"global\";
+        {
+            var a = \"outer\";
+            {
+                var b = \"inner b\";
+                var a = \"inner\";
+                print a;
+                print b;
+            }
+            print a;
+        }
+        print a;
+    ");
+    assert_eq!(printed, ["inner", "inner b", "outer", "global"]);
+}
+
+#[test]
+fn for_loop_variable_and_body_locals() {
+    let printed = run("
+        for (var i = 0; i < 2; i = i + 1) {
+            var doubled = i * 2;
+            print i;
+            print doubled;
+        }
+    ");
+    assert_eq!(printed, ["0", "0", "1", "2"]);
+}
+
+#[test]
+fn methods_see_this_and_super() {
+    let printed = run("
+        class A {
+            name() { return \"A\"; }
+        }
+        class B < A {
+            init(suffix) { this.suffix = suffix; }
+            name() {
+                var prefix = super.name();
+                return prefix + this.suffix;
+            }
+        }
+        print B(\"B\").name();
+    ");
+    assert_eq!(printed, ["AB"]);
+}
+
+#[test]
+fn catch_variable_gets_its_own_slot() {
+    let printed = run("
+        {
+            var before = \"before\";
+            try {
+                var inside = \"inside\";
+                throw inside;
+            } catch (e) {
+                var after = \"after\";
+                print before;
+                print e;
+                print after;
+            }
+        }
+    ");
+    assert_eq!(printed, ["before", "inside", "after"]);
+}
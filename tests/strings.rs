@@ -0,0 +1,64 @@
+mod common;
+
+use common::{run, run_err};
+
+#[test]
+fn plus_concatenates_strings() {
+    let printed = run("
+        var greeting = \"hello\" + \", \" + \"world\";
+        print greeting;
+        print \"\" + \"\";
+        print \"1\" + \"2\";
+    ");
+    assert_eq!(printed, ["hello, world", "", "12"]);
+}
+
+#[test]
+fn strings_compare_lexicographically() {
+    let printed = run("
+        print \"apple\" < \"banana\";
+        print \"b\" > \"abc\";
+        print \"abc\" <= \"abc\";
+        print \"abd\" >= \"abc\";
+        print \"Z\" < \"a\";
+        print \"\" < \"a\";
+    ");
+    assert_eq!(printed, ["true", "true", "true", "true", "true", "true"]);
+}
+
+#[test]
+fn strings_are_not_numbers() {
+    let printed = run("
+        print \"10\" < \"9\";
+        print \"2\" + \"2\";
+    ");
+    assert_eq!(printed, ["true", "22"]);
+}
+
+#[test]
+fn mixed_operands_to_plus_are_errors() {
+    for code in [
+        "print \"a\" + 1;",
+        "print 1 + \"a\";",
+        "print \"a\" + nil;",
+        "print true + \"a\";",
+    ] {
+        let (_, error) = run_err(code);
+        assert_eq!(
+            error.message(),
+            "Operands must be two numbers or two strings."
+        );
+    }
+}
+
+#[test]
+fn mixed_comparisons_are_errors() {
+    let (_, error) = run_err("print \"a\" < 1;");
+    assert_eq!(error.message(), "Operands must be numbers.");
+}
+
+#[test]
+fn other_arithmetic_on_strings_is_an_error() {
+    let (_, error) = run_err("print \"abc\" * 2;");
+    assert_eq!(error.message(), "Operands must be numbers.");
+}